@@ -2,8 +2,12 @@
 Main application entry point for brian
 """
 import atexit
+import os
 import signal
+import subprocess
 import sys
+import threading
+import time
 
 import uvicorn
 from fastapi import FastAPI
@@ -187,6 +191,75 @@ def _repair_mcp_configs():
         print(f"  ⚠ MCP config repair skipped: {e}")
 
 
+def _parent_of(pid):
+    """The parent PID of process `pid`, or None if it can't be read."""
+    if pid == os.getpid():
+        return os.getppid()
+    try:
+        if sys.platform.startswith("linux"):
+            with open(f"/proc/{pid}/stat") as f:
+                # The command name may hold spaces; the fields after it don't.
+                return int(f.read().rsplit(")", 1)[1].split()[1])
+        out = subprocess.run(
+            ["ps", "-o", "ppid=", "-p", str(pid)], capture_output=True, text=True, timeout=5
+        ).stdout
+        return int(out.strip())
+    except (OSError, ValueError, IndexError, subprocess.SubprocessError):
+        return None
+
+
+def _watch_parent():
+    """Exit if the desktop app that spawned us dies without stopping us.
+
+    The app passes its PID as BRIAN_PARENT_PID. It's either our parent, or
+    (in the bundled one-file build) our grandparent, with the PyInstaller
+    bootloader in between. The link is checked by parent PID rather than by
+    probing the app's PID, which another process may reuse once it's gone.
+    On Windows the app already ties the backend to its lifetime with a job
+    object, so this is Unix only.
+    """
+    parent = os.environ.get("BRIAN_PARENT_PID", "")
+    if not parent.isdigit() or sys.platform == "win32":
+        return
+    parent_pid = int(parent)
+    own_parent = os.getppid()
+    link = os.getpid() if own_parent == parent_pid else own_parent
+    if _parent_of(link) != parent_pid:
+        print(f"  ⚠ Desktop app (pid {parent_pid}) is not our parent, not watching it")
+        return
+
+    def _orphaned():
+        return os.getppid() != own_parent or _parent_of(link) != parent_pid
+
+    def _exit_if_orphaned(*_):
+        if _orphaned():
+            print(f"  ⚠ Desktop app (pid {parent_pid}) is gone, shutting down")
+            cleanup_port_file()
+            os._exit(0)
+
+    if sys.platform.startswith("linux") and link == os.getpid():
+        # Have the kernel tell us when the app dies, rather than waiting for
+        # the next poll. It also fires when just the app thread that started
+        # us exits, hence the check before exiting.
+        try:
+            import ctypes
+
+            PR_SET_PDEATHSIG = 1
+            signal.signal(signal.SIGUSR2, _exit_if_orphaned)
+            ctypes.CDLL(None, use_errno=True).prctl(PR_SET_PDEATHSIG, signal.SIGUSR2)
+        except (OSError, AttributeError) as e:
+            print(f"  ⚠ PR_SET_PDEATHSIG unavailable: {e}")
+        # The app may have died before the signal was armed.
+        _exit_if_orphaned()
+
+    def _watch():
+        while True:
+            time.sleep(2)
+            _exit_if_orphaned()
+
+    threading.Thread(target=_watch, name="parent-watchdog", daemon=True).start()
+
+
 def main():
     """Run the application"""
     config = Config()
@@ -204,6 +277,9 @@ def main():
     signal.signal(signal.SIGTERM, _signal_handler)
     signal.signal(signal.SIGINT, _signal_handler)
     
+    # Don't outlive a desktop app that was force-quit
+    _watch_parent()
+    
     # Repair any broken MCP configs from previous installs
     _repair_mcp_configs()
     
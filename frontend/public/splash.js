@@ -0,0 +1,44 @@
+// Splash shown while brian-backend starts. The desktop shell reveals the main
+// window and closes this one on `backend-ready`; this page only reflects
+// progress and offers a retry when startup fails.
+const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+
+const message = document.getElementById('message');
+const retry = document.getElementById('retry');
+const bar = document.querySelector('#progress div');
+
+function showStatus(status) {
+  if (status.state === 'unhealthy') {
+    document.body.classList.add('error');
+    message.textContent = `Brian couldn't start: ${status.reason}`;
+    retry.hidden = false;
+    return;
+  }
+  document.body.classList.remove('error');
+  retry.hidden = true;
+  message.textContent =
+    status.state === 'starting' && status.phase === 'migrating'
+      ? 'Updating your knowledge base…'
+      : 'Starting Brian…';
+}
+
+retry.addEventListener('click', () => {
+  bar.style.width = '0';
+  showStatus({ state: 'starting', phase: 'launching' });
+  invoke('restart_backend').catch((e) =>
+    showStatus({ state: 'unhealthy', reason: e?.message ?? String(e) }),
+  );
+});
+
+listen('backend-status', (event) => showStatus(event.payload));
+listen('backend-startup-progress', (event) => {
+  bar.style.width = `${event.payload.percent}%`;
+});
+listen('backend-warming-up', (event) => {
+  message.textContent = `Backend starting up (HTTP ${event.payload.status})…`;
+});
+listen('backend-error', (event) => showStatus({ state: 'unhealthy', reason: event.payload.message }));
+
+// Startup may already have failed before this page loaded.
+invoke('backend_status').then(showStatus);
@@ -12,12 +12,29 @@ interface UseBackendStatusReturn {
 
 const POLL_INTERVAL_MS = 2000;
 
+/** Payload of the `backend-error` event. */
+interface BackendError {
+  kind:
+    | 'spawn_failed'
+    | 'health_timeout'
+    | 'port_conflict'
+    | 'terminated'
+    | 'unresponsive'
+    | 'no_home_dir'
+    | 'pre_spawn_failed'
+    | 'no_port_file';
+  message: string;
+  detail: string | null;
+  /** The sidecar's last output lines, when they help explain the failure. */
+  log_tail: string[];
+}
+
 /**
  * Hook to monitor backend readiness via Tauri events and HTTP polling fallback.
  *
  * Listens for:
  * - `backend-ready`  → payload is the port number; transitions to 'ready'
- * - `backend-error`  → transitions to 'error' with message
+ * - `backend-error`  → `{ kind, message, detail, log_tail }`; transitions to 'error' with message
  *
  * Also polls GET /health every 2s while in 'connecting' state as a fallback.
  * When the backend is discovered (via event or poll), the port is stored
@@ -101,10 +118,10 @@ export function useBackendStatus(): UseBackendStatusReturn {
         markReady(event.payload);
       });
 
-      unlistenErrorRef.current = await listen<string>(
+      unlistenErrorRef.current = await listen<BackendError>(
         'backend-error',
         (event) => {
-          markError(event.payload ?? 'Unknown backend error');
+          markError(event.payload?.message ?? 'Unknown backend error');
         },
       );
     } catch {
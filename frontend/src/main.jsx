@@ -1,5 +1,6 @@
 import { StrictMode } from 'react'
 import { createRoot } from 'react-dom/client'
+import { invoke, isTauri } from '@tauri-apps/api/core'
 import './index.css'
 import App from './App.jsx'
 import { SettingsProvider } from './contexts/SettingsContext'
@@ -42,3 +43,8 @@ createRoot(document.getElementById('root')).render(
     </SettingsProvider>
   </StrictMode>,
 )
+
+// Tell the desktop shell the first frame is painted, so it can show the window.
+if (isTauri()) {
+  requestAnimationFrame(() => invoke('app_ready'))
+}
@@ -0,0 +1,75 @@
+//! The document the backend publishes about its features (a capabilities
+//! list or its OpenAPI schema), so the frontend can gate UI on what the
+//! running backend supports instead of assuming.
+
+use std::sync::{Mutex, PoisonError};
+
+use reqwest::StatusCode;
+use tauri::{AppHandle, Manager};
+
+use crate::proxy::BackendClient;
+
+/// Managed cache of the current sidecar's document, cleared on every spawn.
+#[derive(Default)]
+pub(crate) struct CapabilitiesCache(Mutex<Option<serde_json::Value>>);
+
+impl CapabilitiesCache {
+    fn get(&self) -> Option<serde_json::Value> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    fn set(&self, document: Option<serde_json::Value>) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = document;
+    }
+
+    pub fn clear(&self) {
+        self.set(None);
+    }
+}
+
+/// The backend's document, from the cache or the first of
+/// `capabilities_paths` that exists. A backend with none of them has an
+/// empty capabilities set, `{}`.
+pub(crate) async fn fetch(app: &AppHandle) -> Result<serde_json::Value, String> {
+    if let Some(document) = app.state::<CapabilitiesCache>().get() {
+        return Ok(document);
+    }
+
+    let connection = crate::read_backend_connection(app)?;
+    let paths = app.state::<crate::config::BrianConfig>().capabilities_paths.clone();
+    let mut document = serde_json::Value::Object(Default::default());
+    for path in &paths {
+        let url = format!("{}{}", connection.base_url(), path);
+        let request = app.state::<BackendClient>().0.get(&url);
+        let resp = connection
+            .authorize(request)
+            .send()
+            .await
+            .map_err(|e| format!("GET {} failed: {}", url, e))?;
+        match resp.status() {
+            StatusCode::NOT_FOUND => continue,
+            status if status.is_success() => {
+                document = resp
+                    .json()
+                    .await
+                    .map_err(|e| format!("malformed {} response: {}", url, e))?;
+                log::info!("Loaded backend capabilities from {}", path);
+                break;
+            }
+            status => return Err(format!("GET {} returned {}", url, status)),
+        }
+    }
+    app.state::<CapabilitiesCache>().set(Some(document.clone()));
+    Ok(document)
+}
+
+/// Fill the cache in the background once the backend is ready, so the first
+/// `backend_capabilities` call doesn't wait on the backend.
+pub(crate) fn prefetch(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = fetch(&app).await {
+            log::warn!("Failed to fetch backend capabilities: {}", e);
+        }
+    });
+}
@@ -0,0 +1,93 @@
+//! What closing the main window does. With `keep_backend_alive_on_close` it
+//! only hides the window, leaving the backend (and its heartbeat) running
+//! until Quit, so showing the window again picks up where it left off, with
+//! a fresh health probe.
+//! Otherwise destroying it stops the backend, so a close request first asks
+//! the backend at `busy_path` whether it has work in flight; if so the close
+//! is held back and `backend-busy-on-close` lets the frontend ask the user,
+//! who confirms with `confirm_close`.
+
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow, Window};
+
+use crate::config::BrianConfig;
+use crate::proxy::BackendClient;
+use crate::sidecar::{sidecar_name, Sidecars};
+
+/// How long the busy check may take before the backend counts as idle.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Handle `CloseRequested` on the main window: hide it, or defer the close
+/// while the busy check runs and close it for real if the backend is idle.
+pub(crate) fn on_close_requested(window: &Window, api: &tauri::CloseRequestApi) {
+    let app = window.app_handle();
+    if app.state::<BrianConfig>().keep_backend_alive_on_close {
+        api.prevent_close();
+        let _ = window.hide();
+        log::info!("Main window hidden, {} keeps running until Quit", sidecar_name());
+        return;
+    }
+    let asks = !app.state::<BrianConfig>().busy_path.is_empty();
+    if !asks || !app.state::<Sidecars>().is_running() {
+        return;
+    }
+    api.prevent_close();
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match busy_work(&app).await {
+            Some(work) => {
+                log::info!("Backend is busy, asking before closing");
+                let _ = app.emit("backend-busy-on-close", work);
+            }
+            None => destroy_main(&app),
+        }
+    });
+}
+
+/// Show, unminimize and focus `window`. The main window coming back from
+/// being hidden on close also has the backend probed right away, rather than
+/// showing a status up to a heartbeat old.
+pub(crate) fn show(window: &WebviewWindow) {
+    let app = window.app_handle();
+    let hidden = !window.is_visible().unwrap_or(true);
+    let _ = window.show();
+    let _ = window.unminimize();
+    let _ = window.set_focus();
+    let kept_alive = app.state::<BrianConfig>().keep_backend_alive_on_close;
+    if hidden && kept_alive && window.label() == "main" {
+        crate::health::check_now(app);
+    }
+}
+
+/// Close the main window without asking again, stopping the backend.
+pub(crate) fn destroy_main(app: &AppHandle) {
+    if let Some(main) = app.get_webview_window("main") {
+        let _ = main.destroy();
+    }
+}
+
+/// The backend's description of its in-flight work, if it says it has any:
+/// a 2xx from `busy_path` whose JSON body has `"busy": true`. Errors, other
+/// statuses (e.g. 404 from a backend without the endpoint) and timeouts all
+/// count as idle, so a wedged backend never blocks closing.
+async fn busy_work(app: &AppHandle) -> Option<serde_json::Value> {
+    let connection = crate::read_backend_connection(app).ok()?;
+    let url = format!("{}{}", connection.base_url(), app.state::<BrianConfig>().busy_path);
+    let request = app.state::<BackendClient>().0.get(&url).timeout(BUSY_TIMEOUT);
+    let request = connection.authorize(request);
+    let resp = match request.send().await {
+        Ok(resp) if resp.status().is_success() => resp,
+        Ok(resp) => {
+            log::debug!("Busy check returned {}, closing", resp.status());
+            return None;
+        }
+        Err(e) => {
+            log::debug!("Busy check failed: {}, closing", e);
+            return None;
+        }
+    };
+    let body: serde_json::Value = resp.json().await.ok()?;
+    (body["busy"] == true).then_some(body)
+}
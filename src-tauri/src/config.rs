@@ -0,0 +1,579 @@
+//! User configuration loaded from `~/.brian/config.toml`, overlaid by an
+//! uncommitted `config.local.toml` next to it and then by `BRIAN_*`
+//! environment variables.
+
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use log::LevelFilter;
+use serde::Deserialize;
+
+/// How the backend is reached over TCP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Scheme {
+    Http,
+    Https,
+}
+
+impl Scheme {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        }
+    }
+}
+
+/// An extra sidecar run alongside the backend, from a `[[sidecars]]` table.
+/// Its binary must be listed under `bundle.externalBin` too.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ExtraSidecar {
+    pub name: String,
+    /// Command-line arguments, one argv entry each.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// How long it gets to exit after SIGTERM on app exit before it is killed.
+    #[serde(default = "ExtraSidecar::default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+}
+
+impl ExtraSidecar {
+    fn default_shutdown_timeout_secs() -> u64 {
+        5
+    }
+}
+
+/// Tunables read from `~/.brian/config.toml`. Every field is optional in the
+/// file; anything missing keeps the built-in default, so an absent file means
+/// zero-config behavior.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct BrianConfig {
+    /// App log verbosity, `RUST_LOG`-style: a level, optionally followed by
+    /// per-target overrides, e.g. `"debug"` or `"info,app_lib=trace,reqwest=warn"`.
+    /// `RUST_LOG` takes precedence when set.
+    pub log_level: String,
+    /// Profile whose state dir (`~/.brian/<profile>`) to use when
+    /// `BRIAN_PROFILE` isn't set.
+    pub profile: Option<String>,
+    /// `http` (default) or `https`, for backends fronted by TLS.
+    pub scheme: Scheme,
+    /// Host the backend is reached on, `127.0.0.1` unless pointing at a
+    /// backend in a VM, container or on another machine. `BRIAN_BACKEND_HOST`
+    /// takes precedence. The webview's CSP only allows loopback, so the
+    /// frontend must go through `backend_request` for anything else.
+    pub backend_host: String,
+    /// Accept self-signed or otherwise invalid certificates over `https`.
+    /// Only meant for a local backend with its own cert.
+    pub danger_accept_invalid_certs: bool,
+    /// Port to assume when the port file is missing or unreadable.
+    pub fallback_port: u16,
+    /// Never guess the port: with no port file (or announced port), fail
+    /// with `no_port_file` instead of trying the last healthy port or
+    /// `fallback_port`, which may belong to an unrelated service.
+    pub require_port_file: bool,
+    /// Ports `discover_backend` scans when not given a range.
+    pub discover_ports: Vec<u16>,
+    /// How long the last port the backend was healthy on stays a better
+    /// guess than `fallback_port` when there's no port file; 0 disables it.
+    pub last_port_ttl_secs: u64,
+    /// Allow `set_backend_port` in release builds (always allowed in debug).
+    pub allow_port_override: bool,
+    /// Total time the backend has to become healthy after a spawn.
+    pub health_budget_secs: u64,
+    /// The budget instead on a first run, i.e. until `initialized` exists in
+    /// the state dir (written by the backend, or by the app after the first
+    /// successful start).
+    pub cold_start_budget_secs: u64,
+    /// Optional cap on health-check attempts within the time budget.
+    pub health_max_retries: Option<u32>,
+    /// Liveness endpoint probed during startup. Must start with `/`.
+    pub health_path: String,
+    /// Text the `health_path` response body must contain (e.g.
+    /// `"service":"brian"`) for the probe to pass, guarding against a proxy
+    /// that answers 200 for any path. Unset, any 2xx passes. Not checked over
+    /// the Unix socket, which is always our own backend.
+    pub health_body_contains: Option<String>,
+    /// Readiness endpoint probed once liveness passes. Must start with `/`.
+    pub ready_path: String,
+    /// Per-attempt timeout for health checks; 3s when unset.
+    pub health_timeout_secs: Option<u64>,
+    /// Auto-restart stops for good (until a manual restart) once the backend
+    /// crashes more than `crash_loop_max_restarts` times within
+    /// `crash_loop_window_secs`.
+    pub crash_loop_max_restarts: usize,
+    pub crash_loop_window_secs: u64,
+    /// Name of the sidecar binary, for builds that ship a renamed backend.
+    pub sidecar_name: Option<String>,
+    /// Seconds between liveness probes once the backend is ready; 0 disables.
+    pub heartbeat_secs: u64,
+    /// Consecutive missed heartbeats before the backend counts as unhealthy.
+    pub heartbeat_failures: u32,
+    /// Skip a probe while another for the same spawn is still in flight
+    /// (a skipped heartbeat counts as missed) rather than stacking probes up.
+    pub heartbeat_single_flight: bool,
+    /// Respawn the sidecar automatically when it crashes.
+    pub auto_restart: bool,
+    /// Wait before the first auto-restart attempt; doubled for each further one.
+    pub auto_restart_delay_secs: u64,
+    /// Instead of respawning a crashed sidecar, emit
+    /// `backend-restart-suggested` and leave it to a `restart_backend` call,
+    /// so the crash can be looked at first.
+    pub auto_restart_confirm: bool,
+    /// Extra command-line arguments for the sidecar, one argv entry each and
+    /// never through a shell, e.g. `["--workers", "4"]` or
+    /// `["--data-dir", "/Volumes/Brian"]`.
+    pub backend_args: Vec<String>,
+    /// Working directory for the sidecar, created if missing. Relative paths
+    /// are taken from the state dir, which is also the default.
+    pub backend_cwd: Option<PathBuf>,
+    /// Wrapper the sidecar is launched under, as a program and its
+    /// arguments, with the sidecar binary and `backend_args` appended: e.g.
+    /// `["py-spy", "record", "-o", "out.svg", "--"]`. Only honored in debug
+    /// builds or with `allow_backend_launcher`.
+    pub backend_launcher: Vec<String>,
+    pub allow_backend_launcher: bool,
+    /// Script mode: run the backend as this Python (a venv's, a pyenv shim,
+    /// …) with `backend_script_args` instead of the bundled sidecar, in
+    /// release builds too. Debug builds then spawn it rather than expecting
+    /// a backend run by hand.
+    pub backend_interpreter: Option<String>,
+    /// Interpreter arguments that start the backend in script mode, ahead of
+    /// `backend_args`.
+    pub backend_script_args: Vec<String>,
+    /// Shell command run before every sidecar spawn (e.g. to unlock secrets
+    /// from a keychain), which must exit 0 for the spawn to go ahead. The
+    /// `KEY=VALUE` lines it prints are added to the sidecar's environment.
+    pub pre_spawn_command: Option<String>,
+    /// Extra sidecars (e.g. a worker) started, in order, once the backend has
+    /// spawned, and stopped in reverse order before it on app exit.
+    pub sidecars: Vec<ExtraSidecar>,
+    /// Extra environment variables passed to the sidecar. Values whose key
+    /// looks secret (`*_KEY`, `*_TOKEN`, …) are masked in the log.
+    pub backend_env: HashMap<String, String>,
+    /// Extra regexes for secrets to mask in sidecar output, on top of the
+    /// built-in ones (`sk-…` keys, `Bearer …`, `password=…`, …). With a
+    /// capture group, only the group is masked.
+    pub redact_patterns: Vec<String>,
+    /// Roll `logs/backend.log` over once it reaches this size.
+    pub log_max_bytes: u64,
+    /// Number of rotated log files (`backend.log.1` …) to keep.
+    pub log_keep_files: usize,
+    /// Crash bundles (`crash/<timestamp>/`, written when the backend goes
+    /// unhealthy) to keep; 0 disables them.
+    pub crash_bundles_keep: usize,
+    /// Number of recent sidecar output lines kept in memory for
+    /// `recent_backend_logs`.
+    pub log_buffer_lines: usize,
+    /// Longest sidecar output line passed on, in bytes; the rest of a longer
+    /// one is replaced with `…[truncated]`.
+    pub log_max_line_bytes: usize,
+    /// Sidecar output lines per second past which it counts as a log flood:
+    /// `backend-log-flood` is emitted and only a sample of the lines reaches
+    /// `backend.log`, the app log and the webview. 0 disables the check.
+    pub log_flood_lines_per_sec: u64,
+    /// The same, in bytes per second.
+    pub log_flood_bytes_per_sec: u64,
+    /// Markers the sidecar may print on stdout right before its port (e.g.
+    /// `BRIAN_PORT=12345`), used when the port file is missing or stale.
+    pub port_markers: Vec<String>,
+    /// Substrings (matched case-insensitively) that mark a sidecar stderr
+    /// line as a failure to bind because the port is taken.
+    pub port_conflict_patterns: Vec<String>,
+    /// Hide the main window on close instead of stopping the backend, which
+    /// then runs until Quit (tray menu) or `stop_backend`.
+    pub keep_backend_alive_on_close: bool,
+    /// Endpoint asked on window close whether the backend has work in flight
+    /// (`{"busy": true, …}`), in which case the user confirms first. Must
+    /// start with `/`; empty to close without asking.
+    pub busy_path: String,
+    /// Endpoints tried in order for the document `backend_capabilities`
+    /// returns; each must start with `/`.
+    pub capabilities_paths: Vec<String>,
+    /// Endpoint `POST`ed by `reload_backend_config` to have the backend
+    /// re-read its settings. Must start with `/`; empty to skip it.
+    pub reload_path: String,
+    /// Have `reload_backend_config` fall back to SIGHUP on Unix when there's
+    /// no reload endpoint. Off by default, as SIGHUP kills a backend that
+    /// doesn't handle it.
+    pub reload_sighup: bool,
+    /// Endpoint `POST`ed to ask the backend to shut down. Must start with `/`.
+    pub shutdown_path: String,
+    /// How long the sidecar gets to exit after a polite shutdown request
+    /// before it is killed.
+    pub shutdown_timeout_secs: u64,
+    /// Emit the sidecar's CPU and memory use as `backend-metrics` every
+    /// `metrics_interval_secs`.
+    pub metrics: bool,
+    pub metrics_interval_secs: u64,
+    /// Resident memory the sidecar may use, sampled every
+    /// `metrics_interval_secs`; unset for no limit. Staying over it for
+    /// `backend_max_rss_secs` emits `backend-memory-exceeded`.
+    pub backend_max_rss_mb: Option<u64>,
+    pub backend_max_rss_secs: u64,
+    /// Also restart the backend (gracefully) when it stays over the limit.
+    pub backend_max_rss_enforce: bool,
+    /// Endpoint reporting the backend's free disk space (`{"free_bytes":…}`),
+    /// polled once it's healthy. Must start with `/`; empty to skip it.
+    pub storage_path: String,
+    /// Seconds between storage polls; 0 disables them.
+    pub storage_interval_secs: u64,
+    /// Free space below which `backend-storage-warning` is emitted.
+    pub storage_warn_free_bytes: u64,
+}
+
+impl Default for BrianConfig {
+    fn default() -> Self {
+        Self {
+            log_level: "info".to_string(),
+            profile: None,
+            scheme: Scheme::Http,
+            backend_host: "127.0.0.1".to_string(),
+            danger_accept_invalid_certs: false,
+            fallback_port: 8080,
+            require_port_file: false,
+            discover_ports: [8000, 8001, 5000].into_iter().chain(8080..=8090).collect(),
+            last_port_ttl_secs: 7 * 24 * 60 * 60,
+            allow_port_override: false,
+            health_budget_secs: 60,
+            cold_start_budget_secs: 600,
+            health_max_retries: None,
+            health_path: "/health".to_string(),
+            health_body_contains: None,
+            ready_path: "/ready".to_string(),
+            health_timeout_secs: None,
+            crash_loop_max_restarts: 5,
+            crash_loop_window_secs: 300,
+            sidecar_name: None,
+            heartbeat_secs: 15,
+            heartbeat_failures: 3,
+            heartbeat_single_flight: true,
+            auto_restart: false,
+            auto_restart_delay_secs: 2,
+            auto_restart_confirm: false,
+            backend_args: Vec::new(),
+            backend_cwd: None,
+            backend_launcher: Vec::new(),
+            allow_backend_launcher: false,
+            backend_interpreter: None,
+            backend_script_args: vec!["-m".to_string(), "brian.main".to_string()],
+            pre_spawn_command: None,
+            sidecars: Vec::new(),
+            backend_env: HashMap::new(),
+            redact_patterns: Vec::new(),
+            log_max_bytes: 5 * 1024 * 1024,
+            log_keep_files: 3,
+            crash_bundles_keep: 5,
+            log_buffer_lines: 500,
+            log_max_line_bytes: 16 * 1024,
+            log_flood_lines_per_sec: 10_000,
+            log_flood_bytes_per_sec: 8 * 1024 * 1024,
+            port_markers: vec!["BRIAN_PORT=".to_string(), "Listening on :".to_string()],
+            port_conflict_patterns: vec![
+                "address already in use".to_string(),
+                "EADDRINUSE".to_string(),
+                // Windows' wording for WSAEADDRINUSE.
+                "only one usage of each socket address".to_string(),
+            ],
+            keep_backend_alive_on_close: false,
+            busy_path: "/busy".to_string(),
+            capabilities_paths: vec!["/capabilities".to_string(), "/openapi.json".to_string()],
+            reload_path: "/reload".to_string(),
+            reload_sighup: false,
+            shutdown_path: "/shutdown".to_string(),
+            shutdown_timeout_secs: 5,
+            metrics: false,
+            metrics_interval_secs: 5,
+            backend_max_rss_mb: None,
+            backend_max_rss_secs: 30,
+            backend_max_rss_enforce: false,
+            storage_path: "/storage".to_string(),
+            storage_interval_secs: 60,
+            storage_warn_free_bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl BrianConfig {
+    /// Load the config from `path` overlaid by its [`local_path`], then by
+    /// `BRIAN_<FIELD>` environment variables (see [`env_layer`]). Later
+    /// layers win key by key, merging into tables rather than replacing
+    /// them. Missing files are skipped silently; an unreadable or malformed
+    /// one logs an error and is skipped, and if the merged result doesn't
+    /// fit, defaults are used.
+    pub fn load(path: &Path) -> Self {
+        Self::load_with_env(path, std::env::vars())
+    }
+
+    /// [`load`](Self::load) with `vars` standing in for the environment.
+    fn load_with_env(path: &Path, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut merged = toml::Table::new();
+        let mut applied = Vec::new();
+        for path in [path.to_path_buf(), local_path(path)] {
+            if let Some(layer) = read_layer(&path) {
+                merge(&mut merged, layer);
+                applied.push(path.display().to_string());
+            }
+        }
+        let env = env_layer(vars);
+        if !env.is_empty() {
+            let names: Vec<String> =
+                env.keys().map(|key| format!("BRIAN_{}", key.to_uppercase())).collect();
+            applied.push(format!("environment ({})", names.join(", ")));
+            merge(&mut merged, env);
+        }
+        let config = match merged.try_into() {
+            Ok(config) => {
+                if !applied.is_empty() {
+                    log::info!("Loaded config from {}", applied.join(", then "));
+                }
+                Self::validated(config)
+            }
+            Err(e) => {
+                log::error!("Malformed config in {}: {} — using defaults", applied.join(", "), e);
+                Self::default()
+            }
+        };
+        config.with_checked_host()
+    }
+
+    /// Just `log_level` from `BRIAN_LOG_LEVEL`, the file at `path` or its
+    /// local layer, silently. The logger is configured before the full
+    /// (logged) [`load`](Self::load) can run.
+    pub fn peek_log_level(path: &Path) -> Option<String> {
+        if let Ok(level) = std::env::var("BRIAN_LOG_LEVEL") {
+            return Some(level);
+        }
+        [local_path(path), path.to_path_buf()].iter().find_map(|path| {
+            let text = std::fs::read_to_string(path).ok()?;
+            let table: toml::Table = toml::from_str(&text).ok()?;
+            table.get("log_level")?.as_str().map(str::to_string)
+        })
+    }
+
+    /// Sanity-check the backend host. Odd hosts only warn: pointing
+    /// elsewhere is deliberate for remote backends.
+    fn with_checked_host(mut self) -> Self {
+        // Stored bare; `base_url` adds the brackets an IPv6 literal needs.
+        let host = self
+            .backend_host
+            .trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        if host.is_empty() {
+            log::error!("backend_host is empty — using 127.0.0.1");
+            self.backend_host = Self::default().backend_host;
+            return self;
+        }
+        if matches!(host.as_str(), "0.0.0.0" | "::") {
+            log::warn!(
+                "backend_host {} is a wildcard address, not a host; it may not reach the backend",
+                host
+            );
+        } else if !is_loopback(&host) {
+            log::warn!(
+                "backend_host is {}: backend traffic leaves this machine{}",
+                host,
+                if self.scheme == Scheme::Http { " unencrypted" } else { "" }
+            );
+        }
+        self.backend_host = host;
+        self
+    }
+
+    /// Replace invalid values with their defaults, logging each one.
+    fn validated(mut self) -> Self {
+        let defaults = Self::default();
+        for (name, path, default) in [
+            ("health_path", &mut self.health_path, defaults.health_path),
+            ("ready_path", &mut self.ready_path, defaults.ready_path),
+            ("shutdown_path", &mut self.shutdown_path, defaults.shutdown_path),
+        ] {
+            if !path.starts_with('/') {
+                log::error!("{} {:?} must start with '/' — using {}", name, path, default);
+                *path = default;
+            }
+        }
+        for (name, path, default) in [
+            ("busy_path", &mut self.busy_path, defaults.busy_path),
+            ("reload_path", &mut self.reload_path, defaults.reload_path),
+            ("storage_path", &mut self.storage_path, defaults.storage_path),
+        ] {
+            if !path.is_empty() && !path.starts_with('/') {
+                log::error!("{} {:?} must start with '/' — using {}", name, path, default);
+                *path = default;
+            }
+        }
+        self.capabilities_paths.retain(|path| {
+            let valid = path.starts_with('/');
+            if !valid {
+                log::error!("capabilities_paths entry {:?} must start with '/', ignoring it", path);
+            }
+            valid
+        });
+        self
+    }
+}
+
+/// The per-machine layer over `path`: `config.local.toml` for `config.toml`.
+fn local_path(path: &Path) -> PathBuf {
+    path.with_extension("local.toml")
+}
+
+/// The table in the file at `path`, or `None` if it's missing (silently) or
+/// can't be read or parsed (logged).
+fn read_layer(path: &Path) -> Option<toml::Table> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return None,
+        Err(e) => {
+            log::error!("Failed to read {}: {} — skipping it", path.display(), e);
+            return None;
+        }
+    };
+    match toml::from_str(&text) {
+        Ok(table) => Some(table),
+        Err(e) => {
+            log::error!("Malformed {}: {} — skipping it", path.display(), e);
+            None
+        }
+    }
+}
+
+/// The environment layer: each `BRIAN_<FIELD>` in `vars` naming a config
+/// field, e.g. `BRIAN_HEARTBEAT_SECS=30`. Values are read as TOML (so
+/// `true`, `30` and `["--workers", "4"]` work), falling back to a plain
+/// string; one that fits its field neither way is logged and skipped.
+/// Variables that aren't fields (`BRIAN_HOME`, …) are ignored, and so are
+/// [`BUILD_TIME_VARS`].
+fn env_layer(vars: impl IntoIterator<Item = (String, String)>) -> toml::Table {
+    let mut layer = toml::Table::new();
+    for (name, value) in vars {
+        let Some(key) = name.strip_prefix("BRIAN_").map(str::to_lowercase) else {
+            continue;
+        };
+        if key.is_empty() || BUILD_TIME_VARS.contains(&name.as_str()) || !is_field(&key) {
+            continue;
+        }
+        let typed = format!("value = {}", value)
+            .parse::<toml::Table>()
+            .ok()
+            .and_then(|mut table| table.remove("value"));
+        let fits = typed
+            .into_iter()
+            .chain(std::iter::once(toml::Value::String(value.clone())))
+            .find(|value| fits_field(&key, value.clone()));
+        match fits {
+            Some(value) => {
+                layer.insert(key, value);
+            }
+            None => log::error!("Ignoring {}={:?}: not a valid {}", name, value, key),
+        }
+    }
+    layer
+}
+
+/// Variables read when the app is compiled, which would be confusing to also
+/// honour at runtime.
+const BUILD_TIME_VARS: &[&str] = &["BRIAN_SIDECAR_NAME"];
+
+/// Whether `key` is a config field. Serde ignores unknown keys, so a value
+/// no field could hold (a table holding an array of tables) fails to
+/// deserialize only under a real field's name.
+fn is_field(key: &str) -> bool {
+    let mut impossible = toml::Table::new();
+    let tables = vec![toml::Value::Table(Default::default())];
+    impossible.insert(String::new(), toml::Value::Array(tables));
+    !fits_field(key, toml::Value::Table(impossible))
+}
+
+/// Whether `value` deserializes as field `key`, on its own.
+fn fits_field(key: &str, value: toml::Value) -> bool {
+    let mut table = toml::Table::new();
+    table.insert(key.to_string(), value);
+    table.try_into::<BrianConfig>().is_ok()
+}
+
+/// Overlay `layer` onto `base`: tables present in both are merged key by
+/// key, anything else in `layer` replaces what `base` had.
+fn merge(base: &mut toml::Table, layer: toml::Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(layer)) => merge(base, layer),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn is_loopback(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Parse a `RUST_LOG`-style spec into a default level and per-target levels.
+/// Unrecognized directives are skipped; with no default given, `Info` is used.
+pub(crate) fn parse_log_spec(spec: &str) -> (LevelFilter, Vec<(String, LevelFilter)>) {
+    let mut default = LevelFilter::Info;
+    let mut targets = Vec::new();
+    for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        match directive.split_once('=') {
+            Some((target, level)) => {
+                if let Ok(level) = level.trim().parse() {
+                    targets.push((target.trim().to_string(), level));
+                }
+            }
+            None => {
+                if let Ok(level) = directive.parse() {
+                    default = level;
+                }
+            }
+        }
+    }
+    (default, targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_layer_beats_local_file() {
+        let dir = std::env::temp_dir().join(format!("brian-config-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "heartbeat_secs = 10\nheartbeat_failures = 7\n").unwrap();
+        std::fs::write(local_path(&path), "heartbeat_secs = 20\n").unwrap();
+        let env = [("BRIAN_HEARTBEAT_SECS".to_string(), "42".to_string())];
+
+        let config = BrianConfig::load_with_env(&path, env);
+
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(config.heartbeat_secs, 42);
+        assert_eq!(config.heartbeat_failures, 7);
+    }
+
+    #[test]
+    fn env_layer_skips_non_fields_and_bad_values() {
+        let vars = [
+            ("BRIAN_HOME", "/tmp/brian"),
+            ("BRIAN_HEARTBEAT_SECS", "soon"),
+            ("BRIAN_BACKEND_HOST", "8080"),
+            ("BRIAN_AUTO_RESTART", "true"),
+            ("BRIAN_SIDECAR_NAME", "other-backend"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let layer = env_layer(vars);
+
+        assert!(!layer.contains_key("home"));
+        assert!(!layer.contains_key("sidecar_name"));
+        assert!(!layer.contains_key("heartbeat_secs"));
+        assert_eq!(layer["backend_host"].as_str(), Some("8080"));
+        assert_eq!(layer["auto_restart"].as_bool(), Some(true));
+    }
+}
@@ -0,0 +1,76 @@
+//! How to reach the backend. Besides the plain port file, the sidecar may
+//! write `connection.json` to the state dir, e.g.
+//! `{"scheme": "https", "port": 8443, "token": "…"}`, to describe richer
+//! connection details; whatever it specifies takes precedence.
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::config::Scheme;
+
+/// `connection.json` as written by the sidecar. Every field is optional.
+/// Deliberately not `Debug`, so the token can't end up in a log.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct ConnectionFile {
+    pub scheme: Option<Scheme>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    /// A Unix socket to use instead of TCP.
+    pub socket: Option<PathBuf>,
+    /// Sent as a bearer `Authorization` header on every backend request,
+    /// instead of the app's own `BRIAN_AUTH_TOKEN`.
+    pub token: Option<String>,
+}
+
+pub(crate) fn connection_file() -> PathBuf {
+    crate::state_dir().join("connection.json")
+}
+
+/// The parsed `connection.json`, if there is one. A malformed file (possibly
+/// caught half-written) counts as absent.
+pub(crate) fn read_file() -> Option<ConnectionFile> {
+    let path = connection_file();
+    let text = std::fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&text) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            log::debug!("Ignoring malformed {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Resolved connection details, from `read_backend_connection`. Not `Debug`
+/// either, for the token's sake.
+#[derive(Clone)]
+pub(crate) struct Connection {
+    pub scheme: Scheme,
+    /// Stored bare; [`base_url`](Self::base_url) adds IPv6 brackets.
+    pub host: String,
+    pub port: u16,
+    pub socket: Option<PathBuf>,
+    pub token: Option<String>,
+}
+
+impl Connection {
+    /// e.g. `http://127.0.0.1:8080`. Everything that talks to the backend over
+    /// TCP builds URLs from this.
+    pub fn base_url(&self) -> String {
+        if self.host.contains(':') {
+            // An IPv6 literal.
+            format!("{}://[{}]:{}", self.scheme.as_str(), self.host, self.port)
+        } else {
+            format!("{}://{}:{}", self.scheme.as_str(), self.host, self.port)
+        }
+    }
+
+    /// `request` with the token, if any, as a bearer `Authorization` header.
+    pub fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
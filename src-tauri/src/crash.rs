@@ -0,0 +1,111 @@
+//! Crash bundles: when the backend ends up `Unhealthy` for good (not merely
+//! missing heartbeats), everything support asks for is written to
+//! `crash/<timestamp>/` in the state dir, so a failed startup can be
+//! diagnosed from one folder.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::BrianConfig;
+use crate::logs::BackendLog;
+use crate::sidecar::is_sensitive_env;
+use crate::status::{BackendStatus, StatusEntry, StatusHistory};
+
+fn crash_dir() -> PathBuf {
+    crate::state_dir().join("crash")
+}
+
+/// Contents of `error.json`.
+#[derive(Serialize)]
+struct Report {
+    status: BackendStatus,
+    /// Recent transitions, oldest first.
+    history: Vec<StatusEntry>,
+    diagnostics: crate::diagnostics::Diagnostics,
+}
+
+/// Write a bundle for `status` on a background thread, then emit
+/// `crash-bundle-written` with its path and prune all but the newest
+/// `crash_bundles_keep` (0 disables bundles).
+pub(crate) fn spawn_write(app: &AppHandle, status: BackendStatus) {
+    let keep = app.state::<BrianConfig>().crash_bundles_keep;
+    if keep == 0 || crate::mock::active(app) {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || match write(&app, status) {
+        Ok(dir) => {
+            log::info!("Wrote crash bundle to {}", dir.display());
+            let _ = app.emit("crash-bundle-written", dir.display().to_string());
+            if let Err(e) = prune(keep) {
+                log::warn!("Failed to prune {}: {}", crash_dir().display(), e);
+            }
+        }
+        Err(e) => log::warn!("Failed to write crash bundle: {}", e),
+    });
+}
+
+/// The backend logs (zipped, as `export_backend_log` writes them), the port
+/// and connection files, the resolved config and `error.json`, with secrets
+/// masked.
+fn write(app: &AppHandle, status: BackendStatus) -> io::Result<PathBuf> {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f").to_string();
+    let dir = crash_dir().join(stamp);
+    std::fs::create_dir_all(&dir)?;
+
+    crate::logs::export(&app.state::<BackendLog>().files(), &dir.join("backend-logs.zip"))?;
+    copy_if_exists(crate::port_file(), &dir.join("port"))?;
+    if let Ok(text) = std::fs::read_to_string(crate::connection::connection_file()) {
+        let masked = match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(mut json) => {
+                if let Some(token) = json.get_mut("token").filter(|token| !token.is_null()) {
+                    *token = "***".into();
+                }
+                json.to_string()
+            }
+            Err(_) => "(malformed; not copied in case it holds a token)".to_string(),
+        };
+        std::fs::write(dir.join("connection.json"), masked)?;
+    }
+
+    let mut config = app.state::<BrianConfig>().inner().clone();
+    for (key, value) in config.backend_env.iter_mut() {
+        if is_sensitive_env(key) {
+            *value = "***".to_string();
+        }
+    }
+    std::fs::write(dir.join("config.txt"), format!("{:#?}\n", config))?;
+
+    let report = Report {
+        status,
+        history: app.state::<StatusHistory>().entries(),
+        diagnostics: crate::diagnostics::collect(app),
+    };
+    let json = serde_json::to_string_pretty(&report).map_err(io::Error::other)?;
+    std::fs::write(dir.join("error.json"), json)?;
+    Ok(dir)
+}
+
+fn copy_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match std::fs::copy(from, to) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Remove all but the newest `keep` bundles. Their names sort by time.
+fn prune(keep: usize) -> io::Result<()> {
+    let mut bundles: Vec<PathBuf> = std::fs::read_dir(crash_dir())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    bundles.sort();
+    let excess = bundles.len().saturating_sub(keep);
+    for path in &bundles[..excess] {
+        std::fs::remove_dir_all(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! A snapshot of everything useful in a startup bug report, for the
+//! frontend's "Copy diagnostics" button.
+
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::config::BrianConfig;
+use crate::logs::{LogLine, LogRate, LogRateStats, RecentLogs};
+use crate::sidecar::{redact_env, sidecar_name, AutoRestart};
+use crate::status::{BackendState, BackendStatus};
+
+/// How many recent sidecar log lines a report includes.
+const LOG_LINES: usize = 200;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Diagnostics {
+    app_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    /// `"debug"` or `"release"`.
+    build: &'static str,
+    sidecar_name: &'static str,
+    status: BackendStatus,
+    /// Whether crashes are currently followed by a respawn.
+    auto_restart: bool,
+    /// The port the backend is believed to be on, after fallbacks; `None`
+    /// when `require_port_file` leaves nothing to go on.
+    port: Option<u16>,
+    port_file: PortFile,
+    /// `backend_env` from the config, sensitive values masked.
+    backend_env: Vec<String>,
+    log_rate: LogRateStats,
+    recent_logs: Vec<LogLine>,
+}
+
+#[derive(Debug, Serialize)]
+struct PortFile {
+    path: String,
+    /// Raw contents, or `None` if the file is missing or unreadable.
+    contents: Option<String>,
+    /// Last modification, in milliseconds since the Unix epoch.
+    modified_ms: Option<u64>,
+}
+
+pub(crate) fn collect(app: &AppHandle) -> Diagnostics {
+    let config = app.state::<BrianConfig>();
+    let path = crate::port_file();
+    let modified_ms = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64);
+
+    let mut env: Vec<(String, String)> = config
+        .backend_env
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    env.sort();
+
+    Diagnostics {
+        app_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        build: if cfg!(debug_assertions) { "debug" } else { "release" },
+        sidecar_name: sidecar_name(),
+        status: app.state::<BackendState>().get(),
+        auto_restart: app.state::<AutoRestart>().enabled(),
+        port: crate::read_backend_port(app).ok(),
+        port_file: PortFile {
+            path: path.display().to_string(),
+            contents: std::fs::read_to_string(path).ok(),
+            modified_ms,
+        },
+        backend_env: redact_env(&env),
+        log_rate: app.state::<LogRate>().stats(),
+        recent_logs: app.state::<RecentLogs>().recent(Some(LOG_LINES)),
+    }
+}
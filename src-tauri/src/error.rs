@@ -0,0 +1,87 @@
+//! Errors returned by the I/O-bound commands, so the frontend can tell a
+//! backend that's slow to answer from one that said no.
+
+use std::future::Future;
+use std::time::Duration;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CommandErrorKind {
+    /// The command ran, but the operation failed.
+    Failed,
+    /// The command gave up after its overall deadline.
+    TimedOut,
+    /// A newer call of the same command superseded this one.
+    Cancelled,
+    /// The backend doesn't offer the operation at all.
+    Unsupported,
+}
+
+/// Serialized as `{"kind": "timed_out", "message": "…"}`; `message` is for
+/// display, `kind` for branching.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct CommandError {
+    pub kind: CommandErrorKind,
+    pub message: String,
+}
+
+impl CommandError {
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self {
+            kind: CommandErrorKind::Cancelled,
+            message: message.into(),
+        }
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        Self {
+            kind: CommandErrorKind::Unsupported,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self {
+            kind: CommandErrorKind::Failed,
+            message,
+        }
+    }
+}
+
+/// Run `fut`, failing with `TimedOut` if it takes longer than `timeout`.
+/// `what` names the operation in the message, e.g. "restarting the backend".
+pub(crate) async fn with_timeout<T>(
+    what: &str,
+    timeout: Duration,
+    fut: impl Future<Output = Result<T, String>>,
+) -> Result<T, CommandError> {
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(result) => result.map_err(CommandError::from),
+        Err(_) => {
+            log::warn!("Gave up {} after {}s", what, timeout.as_secs());
+            Err(CommandError {
+                kind: CommandErrorKind::TimedOut,
+                message: format!("timed out {} after {}s", what, timeout.as_secs()),
+            })
+        }
+    }
+}
+
+/// Run blocking `f` on the blocking pool under [`with_timeout`], so file and
+/// process-table work doesn't stall a runtime worker. On timeout `f` still
+/// runs to completion, its result dropped.
+pub(crate) async fn blocking_with_timeout<T: Send + 'static>(
+    what: &str,
+    timeout: Duration,
+    f: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, CommandError> {
+    let task = tauri::async_runtime::spawn_blocking(f);
+    with_timeout(what, timeout, async move {
+        task.await.map_err(|e| format!("failed {}: {}", what, e))?
+    })
+    .await
+}
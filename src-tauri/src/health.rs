@@ -0,0 +1,992 @@
+//! Backend health checking. Probes go over TCP to the port from the port
+//! file, or over the backend's Unix domain socket when it exposes one, which
+//! guarantees we're talking to our own backend rather than whatever else
+//! happens to hold the port.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::net::TcpStream;
+use tokio::sync::Notify;
+
+use crate::config::BrianConfig;
+use crate::logs::RecentLogs;
+use crate::connection::Connection;
+use crate::{http_client, read_backend_connection, read_backend_port};
+use crate::sidecar::{self, is_current_sidecar, sidecar_name, AutoRestart, StdoutPort};
+use crate::status::{set_status, BackendState, BackendStatus, ErrorKind, StartupPhase};
+
+/// Outcome of a single health probe.
+enum Probe {
+    /// The backend answered with a 2xx.
+    Healthy,
+    /// The backend answered, but with a non-2xx status.
+    Status(u16),
+    /// Connected, but no response within the per-attempt timeout.
+    TimedOut,
+    /// No usable response: refused, reset, or malformed.
+    Failed(String),
+}
+
+/// Payload of `backend-startup-progress`, emitted while waiting for the port
+/// file and before every probe so a splash can draw a determinate bar:
+///
+/// - `phase` — `"waiting_for_port"` or `"probing"`
+/// - `attempt` — the probe about to run, `0` while waiting for the port file
+/// - `max_attempts` — `health_max_retries`, or `null` when only time-bounded
+/// - `elapsed_ms` — time since the health check started
+/// - `percent` — `elapsed_ms` as a share of the time budget, 0–99; readiness
+///   is signalled by `backend-ready`, never by reaching 100
+#[derive(Debug, Clone, Serialize)]
+struct StartupProgress {
+    phase: &'static str,
+    attempt: u32,
+    max_attempts: Option<u32>,
+    elapsed_ms: u64,
+    percent: u8,
+}
+
+/// Payload of `backend-warming-up`, emitted when a startup probe reaches the
+/// backend but gets a non-2xx back, so the UI can tell "listening but not
+/// ready" from "not listening yet".
+#[derive(Debug, Clone, Serialize)]
+struct WarmingUp {
+    /// The probed path, `health_path` or `ready_path`.
+    path: String,
+    status: u16,
+}
+
+fn emit_warming_up(app: &AppHandle, path: &str, status: u16) {
+    let _ = app.emit(
+        "backend-warming-up",
+        WarmingUp {
+            path: path.to_string(),
+            status,
+        },
+    );
+}
+
+/// Emits [`StartupProgress`] for one health-check run.
+struct Progress<'a> {
+    app: &'a AppHandle,
+    started: Instant,
+    budget: Duration,
+    max_attempts: Option<u32>,
+}
+
+impl Progress<'_> {
+    fn emit(&self, phase: &'static str, attempt: u32) {
+        let elapsed = self.started.elapsed();
+        let share = elapsed.as_secs_f64() / self.budget.as_secs_f64().max(f64::EPSILON);
+        let _ = self.app.emit(
+            "backend-startup-progress",
+            StartupProgress {
+                phase,
+                attempt,
+                max_attempts: self.max_attempts,
+                elapsed_ms: elapsed.as_millis() as u64,
+                percent: (share * 100.0).min(99.0) as u8,
+            },
+        );
+    }
+}
+
+/// Repeat a suppressed probe failure once every this many attempts, so a long
+/// wait still shows signs of life.
+const REPEAT_EVERY: u32 = 10;
+
+/// Collapses runs of identical probe failures into a single warning with a
+/// running count, e.g. "Health check failed: connection refused ×12", so a slow
+/// start doesn't flood the log with near-identical lines. A new line is logged
+/// whenever the failure changes.
+#[derive(Default)]
+struct FailureLog {
+    last: Option<String>,
+    repeats: u32,
+}
+
+impl FailureLog {
+    fn failed(&mut self, message: String) {
+        if self.last.as_ref() == Some(&message) {
+            self.repeats += 1;
+            if self.repeats % REPEAT_EVERY == 0 {
+                log::warn!("{} ×{}", message, self.repeats);
+            }
+            return;
+        }
+        self.flush();
+        log::warn!("{}", message);
+        self.last = Some(message);
+        self.repeats = 1;
+    }
+
+    /// Log the final count of the current run, unless that was just logged.
+    fn flush(&mut self) {
+        if let Some(last) = &self.last {
+            if self.repeats > 1 && self.repeats % REPEAT_EVERY != 0 {
+                log::warn!("{} ×{}", last, self.repeats);
+            }
+        }
+        self.repeats = 1;
+    }
+
+    fn last(&self) -> Option<&str> {
+        self.last.as_deref()
+    }
+}
+
+/// Timings of the latest startup, returned by `startup_metrics`. Reset on
+/// every spawn; a phase not reached yet is `None`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct StartupTimes {
+    /// Waiting for the backend to write its port file.
+    port_wait_ms: Option<u64>,
+    /// From the first probe to the first HTTP response of any status.
+    connect_ms: Option<u64>,
+    /// From the start of the health check to `Healthy`, in total.
+    ready_ms: Option<u64>,
+    /// Probes made so far.
+    attempts: u32,
+}
+
+/// Managed state holding the latest [`StartupTimes`].
+#[derive(Default)]
+pub(crate) struct StartupMetrics(Mutex<StartupTimes>);
+
+impl StartupMetrics {
+    pub fn get(&self) -> StartupTimes {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut StartupTimes)) {
+        f(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+}
+
+fn millis(duration: Duration) -> Option<u64> {
+    Some(duration.as_millis() as u64)
+}
+
+/// Managed state letting `health_check_now` cut the wait between probes
+/// short.
+#[derive(Default)]
+pub(crate) struct ProbeNow {
+    wake: Notify,
+    /// Health-check tasks alive (starting up or heartbeating).
+    running: AtomicUsize,
+    /// Set on waking from sleep, so the next heartbeat treats a failure as
+    /// final.
+    resumed: AtomicBool,
+    /// The spawn generation (plus one; 0 is none) whose probe is awaiting
+    /// its response.
+    in_flight: AtomicU64,
+}
+
+/// Counts a health-check task as running for as long as it lives.
+struct Running<'a>(&'a AtomicUsize);
+
+impl<'a> Running<'a> {
+    fn new(running: &'a AtomicUsize) -> Self {
+        running.fetch_add(1, Ordering::SeqCst);
+        Self(running)
+    }
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Holds [`ProbeNow::in_flight`] for one probe of a spawn generation.
+struct InFlight<'a> {
+    slot: &'a AtomicU64,
+    owner: u64,
+}
+
+impl<'a> InFlight<'a> {
+    /// `None` if another probe of `generation` holds it. One of an older
+    /// spawn doesn't count: its hang is no reason to fault the new backend.
+    fn acquire(slot: &'a AtomicU64, generation: u64) -> Option<Self> {
+        let owner = generation + 1;
+        let mut current = slot.load(Ordering::SeqCst);
+        loop {
+            if current == owner {
+                return None;
+            }
+            match slot.compare_exchange(current, owner, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return Some(Self { slot, owner }),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        // Unless a newer spawn's probe has taken it over meanwhile.
+        let _ = self.slot.compare_exchange(self.owner, 0, Ordering::SeqCst, Ordering::SeqCst);
+    }
+}
+
+/// Probe right away: wake a running health check out of its backoff or
+/// heartbeat interval, or start a fresh check if none is running.
+pub(crate) fn check_now(app: &AppHandle) {
+    let now = app.state::<ProbeNow>();
+    if now.running.load(Ordering::SeqCst) > 0 {
+        log::info!("Probing the backend now instead of waiting out the backoff");
+        now.wake.notify_waiters();
+        return;
+    }
+    log::info!("No health check running, starting one");
+    let status = app.state::<BackendState>().get();
+    if matches!(status, BackendStatus::Unhealthy { .. } | BackendStatus::Stopped) {
+        // The check gives up straight away on either.
+        set_status(app, BackendStatus::LAUNCHING);
+    }
+    spawn_health_check(app.clone(), sidecar::current_generation(app));
+}
+
+/// [`check_now`] after the machine wakes from sleep: a heartbeating backend
+/// that fails its next probe is `Unhealthy` right away, and restarted if
+/// auto-restart is on.
+pub(crate) fn check_after_resume(app: &AppHandle) {
+    app.state::<ProbeNow>().resumed.store(true, Ordering::SeqCst);
+    check_now(app);
+}
+
+/// Sleep for `duration`, or less if `health_check_now` is called meanwhile.
+async fn pause(app: &AppHandle, duration: Duration) {
+    let wake = &app.state::<ProbeNow>().wake;
+    tokio::select! {
+        _ = tokio::time::sleep(duration) => {}
+        _ = wake.notified() => {}
+    }
+}
+
+/// How long a bare TCP connect may take before the port is considered dead.
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Scale `delay` by a random factor in `[1 - fraction, 1 + fraction]`.
+fn with_jitter(delay: Duration, fraction: f64) -> Duration {
+    delay.mul_f64(rand::random_range(1.0 - fraction..=1.0 + fraction))
+}
+
+/// Poll the backend until it is ready, moving to `Healthy` on success or
+/// `Unhealthy` once the time budget runs out. `health_path` (`/health`) is the
+/// liveness signal (and what auto-restart counts as "came up"); once it passes
+/// we poll `ready_path` (`/ready`), reporting `Starting { Migrating }` until
+/// that passes too. A backend without a readiness endpoint (404) is ready as
+/// soon as it is alive. Once ready, the task carries on as the sidecar's
+/// [`heartbeat`]. It stops without touching the status as soon as spawn
+/// `generation` is superseded, so a restart never races an older check.
+/// The backend's Unix socket is preferred over TCP whenever it is present.
+pub(crate) fn spawn_health_check(app: AppHandle, generation: u64) {
+    if crate::mock::active(&app) {
+        log::info!("Mock backend on, skipping the health check");
+        return;
+    }
+    let config = app.state::<BrianConfig>().inner().clone();
+    tauri::async_runtime::spawn(async move {
+        let probe_now = app.state::<ProbeNow>();
+        let _running = Running::new(&probe_now.running);
+        // Backoff between attempts starts at 250ms and doubles up to a 5s cap,
+        // with ±20% jitter so several windows don't probe in lockstep. We stop
+        // after a time budget (default 60s, 10min on a first run) rather than a
+        // retry count, so slow and fast machines get the same wall-clock
+        // allowance.
+        const INITIAL_DELAY: Duration = Duration::from_millis(250);
+        const MAX_DELAY: Duration = Duration::from_secs(5);
+        const JITTER: f64 = 0.2;
+        let cold_start = is_cold_start();
+        let time_budget = if cold_start {
+            let budget = config.cold_start_budget_secs.max(config.health_budget_secs);
+            log::info!(
+                "No {} yet, treating this as a first run and allowing {}s to start",
+                cold_start_marker().display(),
+                budget
+            );
+            let _ = app.emit("backend-cold-start", budget);
+            Duration::from_secs(budget)
+        } else {
+            Duration::from_secs(config.health_budget_secs)
+        };
+        let max_retries = config.health_max_retries.unwrap_or(u32::MAX);
+
+        // Bound every attempt, so a backend that accepts connections but
+        // never answers can't stall the loop past its budget. Built per
+        // spawn, so a restart always gets a fresh client.
+        let attempt_timeout = Duration::from_secs(config.health_timeout_secs.unwrap_or(3));
+        let client = http_client(&config)
+            .timeout(attempt_timeout)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+
+        let progress = Progress {
+            app: &app,
+            started: Instant::now(),
+            budget: time_budget,
+            max_attempts: config.health_max_retries,
+        };
+        let metrics = app.state::<StartupMetrics>();
+        metrics.update(|times| *times = StartupTimes::default());
+        wait_for_port_file(&app, &progress).await;
+        metrics.update(|times| times.port_wait_ms = millis(progress.started.elapsed()));
+
+        let mut port = match read_backend_port(&app) {
+            Ok(port) => port,
+            Err(e) => return no_port_file(&app, e),
+        };
+        log::info!("Health checking backend on port {} (from port file)…", port);
+        warn_if_port_file_stale();
+
+        let started = Instant::now();
+        let mut delay = INITIAL_DELAY;
+        let mut attempt: u32 = 0;
+        let mut alive = false;
+        let mut failures = FailureLog::default();
+        loop {
+            attempt += 1;
+
+            if !is_current_sidecar(&app, generation) {
+                log::info!("Abandoning health check: superseded by a newer spawn");
+                return;
+            }
+            metrics.update(|times| times.attempts = attempt);
+            // Something else already ended this start: a detected port
+            // conflict, or the backend stopping (or being stopped) meanwhile.
+            match app.state::<BackendState>().get() {
+                BackendStatus::Unhealthy { reason, .. } => {
+                    log::info!("Abandoning health check: {}", reason);
+                    return;
+                }
+                BackendStatus::Stopped => {
+                    log::info!("Abandoning health check: backend stopped");
+                    return;
+                }
+                _ => {}
+            }
+
+            // Re-read port file each attempt — sidecar may update it after startup
+            let connection = match read_backend_connection(&app) {
+                Ok(connection) => connection,
+                Err(e) => return no_port_file(&app, e),
+            };
+            if connection.port != port {
+                log::info!("Port file updated: {} → {}", port, connection.port);
+                port = connection.port;
+            }
+
+            let socket = connection.socket.clone();
+            log::debug!(
+                "Health check attempt {} on {} ({:.1}s elapsed)…",
+                attempt,
+                socket
+                    .as_ref()
+                    .map(|p| format!("socket {}", p.display()))
+                    .unwrap_or_else(|| format!("port {}", port)),
+                started.elapsed().as_secs_f32()
+            );
+            progress.emit("probing", attempt);
+            if attempt == 1 {
+                let (base, via) = match socket {
+                    Some(ref socket) => (String::new(), format!(" over socket {}", socket.display())),
+                    None => (connection.base_url(), String::new()),
+                };
+                log::info!(
+                    "Probing liveness at {}{} and readiness at {}{}{}",
+                    base,
+                    config.health_path,
+                    base,
+                    config.ready_path,
+                    via
+                );
+            }
+
+            let shared = (&app, &client, &config, &connection);
+            let probe_path = |path| async move {
+                let (app, client, config, connection) = shared;
+                let timeout = attempt_timeout;
+                probe_single_flight(app, generation, client, config, connection, path, timeout)
+                    .await
+                    .unwrap_or_else(|| Probe::Failed("previous probe still in flight".to_string()))
+            };
+            if !alive {
+                let result = probe_path(&config.health_path).await;
+                if matches!(result, Probe::Healthy | Probe::Status(_)) {
+                    metrics.update(|times| {
+                        times.connect_ms = times.connect_ms.or(millis(started.elapsed()));
+                    });
+                }
+                match result {
+                    Probe::Healthy => {
+                        failures.flush();
+                        log::info!("{} is alive on port {} (attempt {})", sidecar_name(), port, attempt);
+                        app.state::<AutoRestart>().mark_healthy();
+                        alive = true;
+                    }
+                    Probe::Status(status) => {
+                        emit_warming_up(&app, &config.health_path, status);
+                        failures.failed(format!("Health check returned non-success status: {}", status));
+                    }
+                    Probe::TimedOut => failures.failed(format!(
+                        "Health check timed out after {}s without a response",
+                        attempt_timeout.as_secs()
+                    )),
+                    Probe::Failed(e) => failures.failed(format!("Health check failed: {}", e)),
+                }
+            }
+
+            if alive {
+                let ready = match probe_path(&config.ready_path).await {
+                    Probe::Healthy => {
+                        log::info!("{} is ready on port {} (attempt {})", sidecar_name(), port, attempt);
+                        true
+                    }
+                    Probe::Status(404) => {
+                        log::info!(
+                            "{} has no {} endpoint, treating {} as readiness",
+                            sidecar_name(),
+                            config.ready_path,
+                            config.health_path
+                        );
+                        true
+                    }
+                    Probe::Status(status) => {
+                        log::info!("{} is alive but not ready yet ({})", sidecar_name(), status);
+                        emit_warming_up(&app, &config.ready_path, status);
+                        if is_current_sidecar(&app, generation) {
+                            set_status(
+                                &app,
+                                BackendStatus::Starting {
+                                    phase: StartupPhase::Migrating,
+                                },
+                            );
+                        }
+                        false
+                    }
+                    Probe::TimedOut => {
+                        failures.failed(format!(
+                            "Readiness check timed out after {}s without a response",
+                            attempt_timeout.as_secs()
+                        ));
+                        false
+                    }
+                    Probe::Failed(e) => {
+                        failures.failed(format!("Readiness check failed: {}", e));
+                        false
+                    }
+                };
+                if ready && is_current_sidecar(&app, generation) {
+                    failures.flush();
+                    if cold_start {
+                        mark_initialized();
+                    }
+                    metrics.update(|times| times.ready_ms = millis(progress.started.elapsed()));
+                    set_status(&app, BackendStatus::Healthy { port });
+                    crate::capabilities::prefetch(&app);
+                    crate::storage::spawn_poll(&app, generation);
+                    heartbeat(&app, generation, &client, &config, attempt_timeout).await;
+                    return;
+                }
+            }
+
+            let elapsed = started.elapsed();
+            if elapsed >= time_budget || attempt >= max_retries {
+                break;
+            }
+            pause(&app, with_jitter(delay, JITTER).min(time_budget - elapsed)).await;
+            delay = (delay * 2).min(MAX_DELAY);
+        }
+
+        failures.flush();
+        if !is_current_sidecar(&app, generation) {
+            return;
+        }
+        let elapsed = started.elapsed().as_secs();
+        let (what, check) = if alive { ("ready", "readiness") } else { ("healthy", "health") };
+        log::error!(
+            "{} did not become {} after {} attempts ({}s of a {}s budget); last failure: {}",
+            sidecar_name(),
+            what,
+            attempt,
+            elapsed,
+            time_budget.as_secs(),
+            failures.last().unwrap_or("none")
+        );
+        // What the backend printed is usually the quickest explanation.
+        let log_tail = app
+            .state::<RecentLogs>()
+            .recent(Some(LOG_TAIL_LINES))
+            .into_iter()
+            .map(|line| line.line)
+            .collect();
+        set_status(
+            &app,
+            BackendStatus::unhealthy(
+                ErrorKind::HealthTimeout,
+                format!("backend {} check failed after {} attempts ({}s)", check, attempt, elapsed),
+                failures.last().map(|last| format!("{} (probed port {})", last, port)),
+            )
+            .with_log_tail(log_tail),
+        );
+    });
+}
+
+/// Sidecar output lines attached to a failed startup's status.
+const LOG_TAIL_LINES: usize = 20;
+
+/// Keep probing liveness while spawn `generation` stays current, so a
+/// backend that hangs without exiting is noticed. After `heartbeat_failures`
+/// misses in a row the backend goes `Unhealthy` and `backend-unhealthy` (with
+/// the miss count) is emitted; the next success emits `backend-recovered`
+/// (with the port) and makes it `Healthy` again. A failed probe of a closed port whose
+/// process (per `backend.pid`) no longer exists means the backend died
+/// without us noticing, and goes through [`sidecar::backend_vanished`].
+async fn heartbeat(
+    app: &AppHandle,
+    generation: u64,
+    client: &reqwest::Client,
+    config: &BrianConfig,
+    timeout: Duration,
+) {
+    if config.heartbeat_secs == 0 {
+        return;
+    }
+    let interval = Duration::from_secs(config.heartbeat_secs);
+    let threshold = config.heartbeat_failures.max(1);
+    let mut failures: u32 = 0;
+    loop {
+        pause(app, interval).await;
+        if !is_current_sidecar(app, generation) {
+            return;
+        }
+        let resumed = app.state::<ProbeNow>().resumed.swap(false, Ordering::SeqCst);
+
+        let connection = match read_backend_connection(app) {
+            Ok(connection) => connection,
+            Err(e) => {
+                log::warn!("Skipping heartbeat: {}", e);
+                continue;
+            }
+        };
+        let port = connection.port;
+        // A probe from a second check on this spawn may still be waiting on
+        // a slow backend.
+        let path = &config.health_path;
+        let result =
+            probe_single_flight(app, generation, client, config, &connection, path, timeout).await;
+        if result.is_none() {
+            log::warn!("Skipping heartbeat: the previous probe hasn't returned");
+        }
+        if !is_current_sidecar(app, generation) {
+            return;
+        }
+        let error = match result {
+            // Counts as missed, so a backend hung on every probe still trips.
+            None => "previous probe still in flight".to_string(),
+            Some(Probe::Healthy) => {
+                if failures >= threshold || resumed {
+                    log::info!("{} recovered on port {}", sidecar_name(), port);
+                    let _ = app.emit("backend-recovered", port);
+                    set_status(app, BackendStatus::Healthy { port });
+                }
+                failures = 0;
+                continue;
+            }
+            Some(Probe::Status(status)) => format!("status {}", status),
+            Some(Probe::TimedOut) => format!("no response within {}s", timeout.as_secs()),
+            Some(Probe::Failed(e)) => {
+                if let Some(reason) = vanished(&connection, generation).await {
+                    log::error!("{} is gone: {}", sidecar_name(), reason);
+                    sidecar::backend_vanished(app, reason);
+                    return;
+                }
+                e
+            }
+        };
+
+        failures += 1;
+        if resumed && failures < threshold {
+            log::warn!("Heartbeat failed right after waking from sleep: {}", error);
+            failures = threshold;
+        }
+        log::warn!("Heartbeat failed ({}/{}): {}", failures, threshold, error);
+        if failures == threshold {
+            log::error!(
+                "{} stopped responding after {} missed heartbeats",
+                sidecar_name(),
+                failures
+            );
+            let _ = app.emit("backend-unhealthy", failures);
+            set_status(
+                app,
+                BackendStatus::unhealthy(
+                    ErrorKind::Unresponsive,
+                    "backend stopped responding",
+                    Some(error),
+                ),
+            );
+            if resumed && generation != sidecar::ATTACHED && app.state::<AutoRestart>().enabled() {
+                log::info!("Restarting {} after waking from sleep", sidecar_name());
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = crate::restart_backend(app).await {
+                        log::error!("Restart after waking from sleep failed: {}", e.message);
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Why our sidecar must have died, if its recorded PID is gone and (over
+/// TCP) its port refuses connections too.
+async fn vanished(connection: &Connection, generation: u64) -> Option<String> {
+    if generation == sidecar::ATTACHED {
+        return None;
+    }
+    if connection.socket.is_none() && port_accepts(&connection.host, connection.port).await {
+        return None;
+    }
+    let pid = sidecar::recorded_pid()?;
+    (!sidecar::process_exists(pid)).then(|| format!("pid {} no longer exists", pid))
+}
+
+/// [`probe`] for spawn `generation`, unless `heartbeat_single_flight` is on
+/// and another of its probes is still awaiting a response (`None`), so a
+/// slow backend doesn't get probes stacked up on it.
+async fn probe_single_flight(
+    app: &AppHandle,
+    generation: u64,
+    client: &reqwest::Client,
+    config: &BrianConfig,
+    connection: &Connection,
+    path: &str,
+    timeout: Duration,
+) -> Option<Probe> {
+    let probe_now = app.state::<ProbeNow>();
+    let in_flight = InFlight::acquire(&probe_now.in_flight, generation);
+    if in_flight.is_none() && config.heartbeat_single_flight {
+        return None;
+    }
+    Some(probe(client, config, connection, path, timeout).await)
+}
+
+/// Probe `path` once, over the Unix socket if there is one and TCP otherwise.
+async fn probe(
+    client: &reqwest::Client,
+    config: &BrianConfig,
+    connection: &Connection,
+    path: &str,
+    timeout: Duration,
+) -> Probe {
+    let token = connection.token.as_deref();
+    match &connection.socket {
+        Some(socket) => probe_socket(socket, path, token, timeout).await,
+        // A leftover port from a previous run would otherwise eat the whole
+        // budget in HTTP timeouts; a failed connect means "re-read next time".
+        None if !port_accepts(&connection.host, connection.port).await => Probe::Failed(format!(
+            "nothing listening on port {} (stale port file?)",
+            connection.port
+        )),
+        None => {
+            let url = format!("{}{}", connection.base_url(), path);
+            let marker = config
+                .health_body_contains
+                .as_deref()
+                .filter(|_| path == config.health_path);
+            probe_tcp(connection.authorize(client.get(&url)), &url, marker).await
+        }
+    }
+}
+
+/// Most ports `discover` probes at once.
+const DISCOVER_CONCURRENCY: usize = 32;
+
+/// Widest range `discover` accepts.
+const DISCOVER_MAX_PORTS: usize = 512;
+
+/// Look for our backend on `range` (inclusive), else on `discover_ports`,
+/// with the liveness probe the startup check uses, `health_body_contains`
+/// included. Returns the first port in scan order that passes. Ports are
+/// scanned without the backend's token, since unrelated services often
+/// listen on them; it's only sent to a port whose signature matched, to
+/// confirm it's ours.
+pub(crate) async fn discover(
+    app: &AppHandle,
+    range: Option<(u16, u16)>,
+) -> Result<Option<u16>, String> {
+    let config = app.state::<BrianConfig>().inner().clone();
+    let ports: Vec<u16> = match range {
+        Some((first, last)) if first > last => {
+            return Err(format!("invalid port range {}-{}", first, last));
+        }
+        Some((first, last)) if usize::from(last - first) >= DISCOVER_MAX_PORTS => {
+            return Err(format!("can't scan more than {} ports at once", DISCOVER_MAX_PORTS));
+        }
+        Some((first, last)) => (first..=last).collect(),
+        None => config.discover_ports.clone(),
+    };
+    log::info!("Looking for the backend on {} ports…", ports.len());
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+    let client = http_client(&config)
+        .timeout(TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .map_err(|e| format!("failed to build HTTP client: {}", e))?;
+    let token = app.state::<sidecar::AuthToken>().get();
+    let connection = |port, token| Connection {
+        scheme: config.scheme,
+        host: config.backend_host.clone(),
+        port,
+        socket: None,
+        token,
+    };
+    let slots = std::sync::Arc::new(tokio::sync::Semaphore::new(DISCOVER_CONCURRENCY));
+    let scans: Vec<_> = ports
+        .into_iter()
+        .map(|port| {
+            let (client, config, slots) = (client.clone(), config.clone(), slots.clone());
+            let connection = connection(port, None);
+            let scan = tauri::async_runtime::spawn(async move {
+                let _slot = slots.acquire().await;
+                let result = probe(&client, &config, &connection, &config.health_path, TIMEOUT);
+                matches!(result.await, Probe::Healthy)
+            });
+            (port, scan)
+        })
+        .collect();
+
+    let mut found = None;
+    for (port, scan) in scans {
+        if found.is_some() {
+            scan.abort();
+        } else if scan.await.unwrap_or(false) {
+            let confirmed = match &token {
+                Some(token) => {
+                    let connection = connection(port, Some(token.clone()));
+                    let probe = probe(&client, &config, &connection, &config.health_path, TIMEOUT);
+                    matches!(probe.await, Probe::Healthy)
+                }
+                None => true,
+            };
+            if confirmed {
+                found = Some(port);
+            } else {
+                log::info!("Port {} looks like the backend but rejected its token", port);
+            }
+        }
+    }
+    match found {
+        Some(port) => log::info!("Found the backend on port {}", port),
+        None => log::info!("No backend found"),
+    }
+    Ok(found)
+}
+
+/// Wait for the sidecar to write its port file, polling every 100ms. Emits
+/// `backend-waiting-for-port` (with the file's path) if it hasn't appeared
+/// after 5s, and gives up after 10s so probing can fall back to the default.
+/// Reports progress about once a second meanwhile. Doesn't wait at all when
+/// a recent last healthy port is cached.
+async fn wait_for_port_file(app: &AppHandle, progress: &Progress<'_>) {
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    const WARN_AFTER: Duration = Duration::from_secs(5);
+    const GIVE_UP_AFTER: Duration = Duration::from_secs(10);
+
+    let path = crate::port_file();
+    let started = Instant::now();
+    let mut warned = false;
+    let mut reported: Option<u64> = None;
+    // Probing the last healthy port is a better bet than waiting; the port
+    // file is still re-read before every attempt.
+    if !path.exists() && !crate::connection::connection_file().exists() {
+        if let Some(port) = crate::last_healthy_port(&app.state::<BrianConfig>()) {
+            log::info!("No port file yet, starting with last healthy port {}", port);
+            return;
+        }
+    }
+    // A port announced on stdout or in `connection.json` makes the file
+    // unnecessary.
+    let connection = crate::connection::connection_file();
+    while !path.exists() && !connection.exists() && app.state::<StdoutPort>().get().is_none() {
+        let elapsed = started.elapsed();
+        if reported != Some(elapsed.as_secs()) {
+            progress.emit("waiting_for_port", 0);
+            reported = Some(elapsed.as_secs());
+        }
+        if elapsed >= GIVE_UP_AFTER {
+            log::warn!(
+                "Port file {} did not appear within {}s, probing anyway",
+                path.display(),
+                GIVE_UP_AFTER.as_secs()
+            );
+            return;
+        }
+        if !warned && elapsed >= WARN_AFTER {
+            log::info!("Still waiting for port file {}…", path.display());
+            let _ = app.emit("backend-waiting-for-port", path.display().to_string());
+            warned = true;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Created by the backend once its one-time setup (model downloads, database
+/// init) is done.
+fn cold_start_marker() -> PathBuf {
+    crate::state_dir().join("initialized")
+}
+
+/// Whether the backend has never finished setting up in this state dir.
+fn is_cold_start() -> bool {
+    !cold_start_marker().exists()
+}
+
+/// Create the marker on the backend's behalf after its first successful
+/// start, so a backend that never writes it isn't given the cold-start budget
+/// every time.
+fn mark_initialized() {
+    let marker = cold_start_marker();
+    if marker.exists() {
+        return;
+    }
+    if let Err(e) = std::fs::write(&marker, "") {
+        log::warn!("Failed to write {}: {}", marker.display(), e);
+    }
+}
+
+/// Warn if the port file predates this launch, i.e. was left by a previous run.
+fn warn_if_port_file_stale() {
+    if crate::port_file_is_stale() {
+        log::warn!(
+            "Port file {} predates this launch and may be stale",
+            crate::port_file().display()
+        );
+    }
+}
+
+/// Give up on a start that has no port to probe because `require_port_file`
+/// forbids guessing one.
+fn no_port_file(app: &AppHandle, error: String) {
+    log::error!("Health check failed: {}", error);
+    set_status(
+        app,
+        BackendStatus::unhealthy(ErrorKind::NoPortFile, "Backend port unknown", Some(error)),
+    );
+}
+
+/// Whether anything accepts TCP connections on `host:port`.
+async fn port_accepts(host: &str, port: u16) -> bool {
+    matches!(
+        tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((host, port))).await,
+        Ok(Ok(_))
+    )
+}
+
+/// Send `request`, a `GET` of `url`. With a `marker`, a 2xx only counts if
+/// the body contains it, so a proxy or captive portal answering 200 for
+/// everything isn't mistaken for the backend.
+async fn probe_tcp(request: reqwest::RequestBuilder, url: &str, marker: Option<&str>) -> Probe {
+    let mut resp = match request.send().await {
+        Ok(resp) if resp.status().is_success() => resp,
+        Ok(resp) => return Probe::Status(resp.status().as_u16()),
+        Err(e) if e.is_timeout() => return Probe::TimedOut,
+        Err(e) => return Probe::Failed(e.to_string()),
+    };
+    let Some(marker) = marker else {
+        return Probe::Healthy;
+    };
+
+    // A health body is tiny; don't buffer whatever a stranger sends back.
+    const MAX_BODY: usize = 64 * 1024;
+    let mut body = Vec::new();
+    loop {
+        match resp.chunk().await {
+            Ok(Some(chunk)) if body.len() < MAX_BODY => body.extend_from_slice(&chunk),
+            Ok(_) => break,
+            Err(e) if e.is_timeout() => return Probe::TimedOut,
+            Err(e) => return Probe::Failed(e.to_string()),
+        }
+    }
+    if String::from_utf8_lossy(&body).contains(marker) {
+        Probe::Healthy
+    } else {
+        Probe::Failed(format!(
+            "{} answered 200 without {:?} in its body, probably not our backend",
+            url, marker
+        ))
+    }
+}
+
+/// The backend's Unix socket, if it has published one. `socket` in the state dir is
+/// either the socket itself or a file containing the socket's path.
+#[cfg(unix)]
+pub(crate) fn backend_socket() -> Option<PathBuf> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let marker = crate::state_dir().join("socket");
+    if std::fs::metadata(&marker).ok()?.file_type().is_socket() {
+        return Some(marker);
+    }
+    let path = PathBuf::from(std::fs::read_to_string(&marker).ok()?.trim());
+    path.exists().then_some(path)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn backend_socket() -> Option<PathBuf> {
+    None
+}
+
+/// Issue a bare `GET` over the Unix socket and read back the status line.
+#[cfg(unix)]
+async fn probe_socket(socket: &Path, path: &str, token: Option<&str>, timeout: Duration) -> Probe {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let exchange = async {
+        let mut stream = tokio::net::UnixStream::connect(socket).await?;
+        let auth = token.map_or_else(String::new, |t| format!("Authorization: Bearer {}\r\n", t));
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+            path, auth
+        );
+        stream.write_all(request.as_bytes()).await?;
+        // Only the status line matters; don't buffer an arbitrarily large body.
+        let mut head = Vec::new();
+        stream.take(1024).read_to_end(&mut head).await?;
+        Ok::<_, std::io::Error>(head)
+    };
+
+    let head = match tokio::time::timeout(timeout, exchange).await {
+        Ok(Ok(head)) => head,
+        Ok(Err(e)) => return Probe::Failed(e.to_string()),
+        Err(_) => return Probe::TimedOut,
+    };
+    let status = String::from_utf8_lossy(&head)
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok());
+    match status {
+        Some(code) if (200..300).contains(&code) => Probe::Healthy,
+        Some(code) => Probe::Status(code),
+        None => Probe::Failed("malformed HTTP response over socket".to_string()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn probe_socket(
+    _socket: &Path,
+    _path: &str,
+    _token: Option<&str>,
+    _timeout: Duration,
+) -> Probe {
+    Probe::Failed("Unix sockets are not supported on this platform".to_string())
+}
@@ -0,0 +1,141 @@
+//! One Brian per profile. The first launch holds an exclusive lock on
+//! `lock` in the state dir; a second launch for the same profile hands its
+//! command-line arguments to the running instance through `activate.json`
+//! and exits, so two apps never fight over one backend and port file.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex, PoisonError};
+
+use fs4::{FileExt, TryLockError};
+use notify::{RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Managed state holding the lock for the app's lifetime.
+pub(crate) struct InstanceLock(Mutex<Option<File>>);
+
+impl InstanceLock {
+    /// Unlock so a new launch can start straight away, even while this
+    /// process is still winding down.
+    pub fn release(&self) {
+        if let Some(file) = self.0.lock().unwrap_or_else(PoisonError::into_inner).take() {
+            // Spelled out so this resolves to fs4 rather than the newer std method.
+            let _ = FileExt::unlock(&file);
+        }
+    }
+}
+
+fn lock_path() -> PathBuf {
+    crate::state_dir().join("lock")
+}
+
+fn activate_path() -> PathBuf {
+    crate::state_dir().join("activate.json")
+}
+
+/// Take the per-profile lock, or `None` if another instance holds it.
+/// Any other failure (e.g. a read-only state dir) is logged and the app runs
+/// without the guard rather than refusing to start.
+pub(crate) fn acquire() -> Option<InstanceLock> {
+    let path = lock_path();
+    let file = std::fs::create_dir_all(crate::state_dir()).and_then(|()| {
+        File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+    });
+    let file = match file {
+        Ok(file) => file,
+        Err(e) => {
+            log::warn!(
+                "Failed to open {}, not guarding against a second instance: {}",
+                path.display(),
+                e
+            );
+            return Some(InstanceLock(Mutex::new(None)));
+        }
+    };
+    match FileExt::try_lock(&file) {
+        Ok(()) => Some(InstanceLock(Mutex::new(Some(file)))),
+        Err(TryLockError::WouldBlock) => None,
+        Err(TryLockError::Error(e)) => {
+            log::warn!(
+                "Failed to lock {}, not guarding against a second instance: {}",
+                path.display(),
+                e
+            );
+            Some(InstanceLock(Mutex::new(None)))
+        }
+    }
+}
+
+/// Pass this launch's arguments to the instance holding the lock.
+pub(crate) fn forward_to_running() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let path = activate_path();
+    let tmp = path.with_extension("json.tmp");
+    // Write then rename, so the running instance never reads half a request.
+    let written = serde_json::to_string(&args)
+        .map_err(std::io::Error::other)
+        .and_then(|json| std::fs::write(&tmp, json))
+        .and_then(|()| std::fs::rename(&tmp, &path));
+    if let Err(e) = written {
+        log::warn!("Failed to forward arguments to the running instance: {}", e);
+    }
+}
+
+/// Watch for activation requests from later launches on a background
+/// thread, bringing the app to the front and emitting `second-instance` with
+/// the forwarded arguments for each one.
+pub(crate) fn watch_activations(app: AppHandle) {
+    let path = activate_path();
+    // Drop any request left over from a launch that raced our own start.
+    let _ = std::fs::remove_file(&path);
+
+    let (tx, rx) = mpsc::channel();
+    let watched = path.clone();
+    let mut watcher = match notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| event.paths.contains(&watched)) {
+            let _ = tx.send(());
+        }
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            log::warn!("Failed to create activation watcher: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(&crate::state_dir(), RecursiveMode::NonRecursive) {
+        log::warn!("Failed to watch {}: {}", crate::state_dir().display(), e);
+        return;
+    }
+
+    std::thread::spawn(move || {
+        // Owned by the thread so watching lasts as long as it does.
+        let _watcher = watcher;
+        while rx.recv().is_ok() {
+            if let Some(args) = take_request(&path) {
+                activated(&app, args);
+            }
+        }
+    });
+}
+
+fn take_request(path: &Path) -> Option<Vec<String>> {
+    let json = std::fs::read_to_string(path).ok()?;
+    let _ = std::fs::remove_file(path);
+    serde_json::from_str(&json).ok()
+}
+
+fn activated(app: &AppHandle, args: Vec<String>) {
+    log::info!("Another launch for this profile was redirected here (args: {:?})", args);
+    // While the splash is up the main window isn't ready to be shown yet.
+    let window = app
+        .get_webview_window(crate::splash::LABEL)
+        .or_else(|| app.get_webview_window("main"));
+    if let Some(window) = window {
+        crate::close::show(&window);
+    }
+    let _ = app.emit("second-instance", args);
+}
@@ -1,159 +1,965 @@
-use std::sync::Mutex;
-use tauri::Emitter;
-use tauri::Manager;
-use tauri_plugin_shell::ShellExt;
-use tauri_plugin_shell::process::{CommandChild, CommandEvent};
-
-/// Holds the sidecar child process so we can kill it on app exit.
-struct SidecarChild(Mutex<Option<CommandChild>>);
-
-/// Read the backend port from ~/.brian/port file.
-/// Falls back to 8080 if the file doesn't exist or can't be read.
-fn read_backend_port() -> u16 {
-    let port_file = dirs::home_dir()
-        .map(|h| h.join(".brian").join("port"))
+mod capabilities;
+mod close;
+mod config;
+mod connection;
+mod crash;
+mod diagnostics;
+mod error;
+mod health;
+mod instance;
+mod logs;
+mod metrics;
+mod mock;
+mod port_watch;
+mod processes;
+mod proxy;
+mod redact;
+mod sidecar;
+mod splash;
+mod sse;
+mod status;
+mod storage;
+mod tray;
+mod version;
+mod wake;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use config::BrianConfig;
+use error::{blocking_with_timeout, with_timeout, CommandError};
+use logs::{BackendLog, LogChunk, LogLine, LogRate, RecentLogs, RotatedLog, RotatingLog};
+use sidecar::{
+    kill_backend, shutdown_backend, sidecar_name, spawn_backend, spawn_backend_async, AutoRestart,
+    RequestedPort, ShuttingDown, Sidecars, StdoutPort,
+};
+use status::{set_status, BackendState, BackendStatus, ErrorKind};
+
+/// The ~/.brian directory, or `$BRIAN_HOME` when set: home of `config.toml`
+/// and, for the default profile, the state shared with the backend (which
+/// inherits the variable). `None` if neither can be resolved.
+fn resolve_brian_dir() -> Option<PathBuf> {
+    match std::env::var_os("BRIAN_HOME").filter(|home| !home.is_empty()) {
+        Some(home) => Some(PathBuf::from(home)),
+        None => dirs::home_dir().map(|h| h.join(".brian")),
+    }
+}
+
+/// [`resolve_brian_dir`], falling back to a directory under the system temp
+/// dir so nothing is written relative to the working directory. Setup
+/// reports the fallback as a `no_home_dir` error.
+fn brian_dir() -> PathBuf {
+    static BRIAN_DIR: OnceLock<PathBuf> = OnceLock::new();
+    BRIAN_DIR
+        .get_or_init(|| resolve_brian_dir().unwrap_or_else(|| std::env::temp_dir().join("brian")))
+        .clone()
+}
+
+static PROFILE: OnceLock<Option<String>> = OnceLock::new();
+
+/// Resolve the active profile once, at startup: `$BRIAN_PROFILE`, else
+/// `profile` from the config file. `"default"` (or nothing) means the
+/// plain ~/.brian layout.
+fn init_profile(config: &BrianConfig) {
+    let profile = std::env::var("BRIAN_PROFILE")
+        .ok()
+        .or_else(|| config.profile.clone())
+        .filter(|p| !p.is_empty() && p != "default")
+        .filter(|p| {
+            let valid = p.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                log::warn!(
+                    "Ignoring profile {:?}: only letters, digits, '-' and '_' are allowed",
+                    p
+                );
+            }
+            valid
+        });
+    log::info!("Using profile {}", profile.as_deref().unwrap_or("default"));
+    let _ = PROFILE.set(profile);
+}
+
+/// The active profile, or `None` for the default one.
+fn profile() -> Option<&'static str> {
+    PROFILE.get().and_then(Option::as_deref)
+}
+
+/// Where this profile's runtime state lives — port file, socket, logs, PID
+/// file: ~/.brian for the default profile, ~/.brian/<profile> otherwise.
+fn state_dir() -> PathBuf {
+    match profile() {
+        Some(profile) => brian_dir().join(profile),
+        None => brian_dir(),
+    }
+}
+
+/// When this process started; a port file older than this was left by a previous run.
+static APP_STARTED: OnceLock<SystemTime> = OnceLock::new();
+
+/// The file the backend writes its listening port to: `$BRIAN_PORT_FILE` when
+/// set to an absolute path (the sidecar inherits the variable, so both sides
+/// agree), otherwise `port` in the profile's state dir. Resolved once so the
+/// initial read and every re-read use the same file.
+fn port_file() -> &'static Path {
+    static PORT_FILE: OnceLock<PathBuf> = OnceLock::new();
+    PORT_FILE.get_or_init(|| match std::env::var_os("BRIAN_PORT_FILE").map(PathBuf::from) {
+        Some(path) if path.is_absolute() => path,
+        Some(path) => {
+            log::warn!(
+                "Ignoring BRIAN_PORT_FILE={}: not an absolute path",
+                path.display()
+            );
+            state_dir().join("port")
+        }
+        None => state_dir().join("port"),
+    })
+}
+
+/// A port set with `set_backend_port`, taking precedence over the port file.
+struct PortOverride(Mutex<Option<u16>>);
+
+impl PortOverride {
+    fn get(&self) -> Option<u16> {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set(&self, port: Option<u16>) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = port;
+    }
+}
+
+/// How to reach the backend: whatever `connection.json` specifies, else the
+/// configured scheme and host, the port from [`port_from_port_file`], and the
+/// Unix socket if the backend published one. A debugging port override beats
+/// them all. Fails only when there's no port to use and
+/// `require_port_file` forbids guessing one.
+fn read_backend_connection(app: &AppHandle) -> Result<connection::Connection, String> {
+    let file = connection::read_file().unwrap_or_default();
+    let config = app.state::<BrianConfig>();
+    let port = match app.state::<PortOverride>().get().or(file.port) {
+        Some(port) => port,
+        None => port_from_port_file(app, &config)?,
+    };
+    let host = file.host.map(|host| host.trim_start_matches('[').trim_end_matches(']').to_string());
+    Ok(connection::Connection {
+        scheme: file.scheme.unwrap_or(config.scheme),
+        host: host.unwrap_or_else(|| config.backend_host.clone()),
+        port,
+        socket: file.socket.or_else(health::backend_socket),
+        token: file.token.or_else(|| app.state::<sidecar::AuthToken>().get()),
+    })
+}
+
+/// The backend port, from [`read_backend_connection`].
+fn read_backend_port(app: &AppHandle) -> Result<u16, String> {
+    read_backend_connection(app).map(|connection| connection.port)
+}
+
+/// The port file, else a port the sidecar announced on stdout. The
+/// announced port also wins over a port file left by a previous launch.
+/// Falls back to the last port the backend was healthy on, then to the
+/// configured port, unless `require_port_file` is set.
+fn port_from_port_file(app: &AppHandle, config: &BrianConfig) -> Result<u16, String> {
+    let from_file = std::fs::read_to_string(port_file())
+        .ok()
+        .and_then(|s| s.trim().parse::<u16>().ok());
+    let from_stdout = app.state::<StdoutPort>().get();
+    match (from_file, from_stdout) {
+        (Some(file), Some(stdout)) if file != stdout && port_file_is_stale() => Ok(stdout),
+        (Some(file), _) => Ok(file),
+        (None, Some(stdout)) => Ok(stdout),
+        (None, None) if config.require_port_file => Err(format!(
+            "no port in {} and require_port_file is set",
+            port_file().display()
+        )),
+        (None, None) => Ok(last_healthy_port(config).unwrap_or(config.fallback_port)),
+    }
+}
+
+fn last_port_file() -> PathBuf {
+    state_dir().join("last_port")
+}
+
+/// Remember `port` as the last one the backend was healthy on, so the next
+/// launch can start probing there instead of at the fallback.
+fn remember_healthy_port(port: u16) {
+    let path = last_port_file();
+    if let Err(e) = std::fs::write(&path, port.to_string()) {
+        log::warn!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// The cached last healthy port, unless it's older than `last_port_ttl_secs`
+/// (0 disables the cache).
+fn last_healthy_port(config: &BrianConfig) -> Option<u16> {
+    if config.last_port_ttl_secs == 0 {
+        return None;
+    }
+    let path = last_port_file();
+    let age = std::fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .ok()?
+        .elapsed()
         .unwrap_or_default();
+    if age > Duration::from_secs(config.last_port_ttl_secs) {
+        return None;
+    }
+    std::fs::read_to_string(&path).ok()?.trim().parse().ok()
+}
+
+/// Whether the port file predates this launch, i.e. was left by a previous run.
+fn port_file_is_stale() -> bool {
+    let Some(started) = APP_STARTED.get() else {
+        return false;
+    };
+    std::fs::metadata(port_file())
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| modified < *started)
+}
 
-    std::fs::read_to_string(&port_file)
+/// Client builder for requests to the backend, honoring the TLS settings.
+fn http_client(config: &BrianConfig) -> reqwest::ClientBuilder {
+    reqwest::Client::builder().danger_accept_invalid_certs(config.danger_accept_invalid_certs)
+}
+
+/// Return the port the backend is currently listening on.
+/// Re-reads the port file on every call since the sidecar may rewrite it after startup.
+#[tauri::command]
+fn get_backend_port(app: AppHandle) -> Result<u16, String> {
+    read_backend_port(&app)
+}
+
+/// Return the canonical base URL the webview should use for backend requests,
+/// so it never hard-codes the transport. A backend that wants a token (see
+/// `connection.json`) is best reached through `backend_request` instead.
+#[tauri::command]
+fn backend_base_url(app: AppHandle) -> Result<String, String> {
+    read_backend_connection(&app).map(|connection| connection.base_url())
+}
+
+/// Point the app at `port` regardless of the port file, e.g. at a backend
+/// launched by hand. Only available in debug builds or with
+/// `allow_port_override` in the config.
+#[tauri::command]
+fn set_backend_port(app: AppHandle, port: u16) -> Result<(), String> {
+    set_port_override(&app, Some(port))
+}
+
+/// Replace the backend with a mock in `status` (a `backend-status`-shaped
+/// object) and emit the matching events, for exercising the UI without a
+/// backend. Debug builds only; stays in effect until the app exits.
+#[tauri::command]
+fn set_mock_backend_status(app: AppHandle, status: mock::MockStatus) -> Result<(), String> {
+    mock::set(&app, status)
+}
+
+/// Undo `set_backend_port`, going back to the port file.
+#[tauri::command]
+fn clear_backend_port_override(app: AppHandle) -> Result<(), String> {
+    set_port_override(&app, None)
+}
+
+fn set_port_override(app: &AppHandle, port: Option<u16>) -> Result<(), String> {
+    if !cfg!(debug_assertions) && !app.state::<BrianConfig>().allow_port_override {
+        return Err(
+            "port override is disabled; set allow_port_override in config.toml".to_string(),
+        );
+    }
+    match port {
+        Some(port) => log::info!("Overriding backend port: {}", port),
+        None => log::info!("Clearing backend port override"),
+    }
+    app.state::<PortOverride>().set(port);
+    if let Ok(port) = read_backend_port(app) {
+        let _ = app.emit("backend-port-changed", port);
+    }
+    Ok(())
+}
+
+/// Serializes restarts: each call takes a ticket, and one that is still
+/// waiting its turn when a newer call arrives is cancelled rather than run.
+#[derive(Default)]
+struct Restarts {
+    latest: AtomicU64,
+    running: tokio::sync::Mutex<()>,
+}
+
+/// Kill the running sidecar (if any) and spawn a fresh one, re-running the
+/// log-streaming and health-check tasks. Lets the UI offer "Reconnect backend".
+#[tauri::command]
+async fn restart_backend(app: AppHandle) -> Result<(), CommandError> {
+    let restarts = app.state::<Restarts>();
+    let ticket = restarts.latest.fetch_add(1, Ordering::SeqCst) + 1;
+    let _running = restarts.running.lock().await;
+    if restarts.latest.load(Ordering::SeqCst) != ticket {
+        return Err(CommandError::cancelled("superseded by a newer restart"));
+    }
+
+    log::info!("Restarting {} sidecar…", sidecar_name());
+    let handle = app.clone();
+    let restart = tauri::async_runtime::spawn_blocking(move || {
+        kill_backend(&handle);
+        // A manual restart gives auto-restart a fresh budget.
+        handle.state::<AutoRestart>().reset();
+        spawn_backend(&handle).map_err(|e| {
+            log::error!("Backend restart failed: {}", e);
+            set_status(&handle, e.status());
+            e.message
+        })
+    });
+    let result = with_timeout("restarting the backend", RESTART_TIMEOUT, async {
+        restart.await.map_err(|e| format!("failed to restart backend: {}", e))?
+    })
+    .await;
+    if let Err(e) = &result {
+        if e.kind != error::CommandErrorKind::Failed {
+            let status = BackendStatus::unhealthy(ErrorKind::SpawnFailed, e.message.clone(), None);
+            set_status(&app, status);
+        }
+    }
+    result
+}
+
+/// Overall deadline for `restart_backend`'s kill and spawn; the health check
+/// runs on afterwards.
+const RESTART_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Stop the backend without quitting: the same polite-then-forced sequence
+/// as app exit, leaving the app `Stopped` until `restart_backend` starts it
+/// again. Its heartbeat ends with it.
+#[tauri::command]
+async fn stop_backend(app: AppHandle) -> Result<(), CommandError> {
+    log::info!("Stopping {} sidecar on request…", sidecar_name());
+    // Polite timeout plus the kill's own grace period, with some slack.
+    let timeout = Duration::from_secs(app.state::<BrianConfig>().shutdown_timeout_secs + 5);
+    // Shutdown blocks while waiting for the process to exit.
+    let stop = tauri::async_runtime::spawn_blocking(move || {
+        shutdown_backend(&app);
+        // Covers the case where nothing was running.
+        set_status(&app, BackendStatus::Stopped);
+    });
+    with_timeout("stopping the backend", timeout, async {
+        stop.await.map_err(|e| format!("failed to stop backend: {}", e))
+    })
+    .await
+}
+
+/// How soon after one relaunch `relaunch_app` refuses another, so a flow
+/// that relaunches on startup can't loop.
+const RELAUNCH_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Touched right before each relaunch.
+fn relaunch_marker() -> PathBuf {
+    state_dir().join("relaunched")
+}
+
+/// Quit and start Brian afresh: every sidecar is shut down the way app exit
+/// does it, a port file left by ours is removed and the instance lock
+/// released, so the new process starts clean. Refused within
+/// `RELAUNCH_COOLDOWN` of the last relaunch.
+#[tauri::command]
+fn relaunch_app(app: AppHandle) -> Result<(), String> {
+    let marker = relaunch_marker();
+    let recent = std::fs::metadata(&marker)
+        .and_then(|m| m.modified())
         .ok()
-        .and_then(|s| s.trim().parse::<u16>().ok())
-        .unwrap_or(8080)
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < RELAUNCH_COOLDOWN);
+    if recent {
+        return Err(format!(
+            "Brian was relaunched less than {}s ago, not relaunching again",
+            RELAUNCH_COOLDOWN.as_secs()
+        ));
+    }
+    if let Err(e) = std::fs::write(&marker, "") {
+        log::warn!("Failed to write {}: {}", marker.display(), e);
+    }
+
+    log::info!("Relaunching Brian, stopping {} sidecar…", sidecar_name());
+    app.state::<ShuttingDown>().0.store(true, Ordering::SeqCst);
+    // Shutdown blocks while waiting for the sidecars to exit.
+    tauri::async_runtime::spawn_blocking(move || {
+        sidecar::shutdown_all(&app);
+        if SIDECAR_SPAWNED.load(Ordering::SeqCst) {
+            for path in [port_file().to_path_buf(), connection::connection_file()] {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        log::warn!("Failed to remove {}: {}", path.display(), e);
+                    }
+                }
+            }
+        }
+        if let Some(lock) = app.try_state::<instance::InstanceLock>() {
+            lock.release();
+        }
+        app.restart();
+    });
+    Ok(())
+}
+
+/// Restart the sidecar on `port`, or on an ephemeral port of the backend's
+/// choosing when `None`, and return the port it comes up healthy on. Backs the
+/// "use a different port" recovery offered after `backend-port-conflict`.
+#[tauri::command]
+async fn rebind_backend(app: AppHandle, port: Option<u16>) -> Result<u16, CommandError> {
+    let timeout = {
+        let config = app.state::<BrianConfig>();
+        Duration::from_secs(config.health_budget_secs.max(config.cold_start_budget_secs) + 15)
+    };
+    with_timeout("rebinding the backend", timeout, rebind(&app, port)).await
+}
+
+async fn rebind(app: &AppHandle, port: Option<u16>) -> Result<u16, String> {
+    log::info!(
+        "Rebinding {} to {}…",
+        sidecar_name(),
+        port.map_or_else(|| "an ephemeral port".to_string(), |p| format!("port {}", p))
+    );
+    kill_backend(app);
+    let requested = app.state::<RequestedPort>();
+    *requested.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(port.unwrap_or(0));
+
+    // Don't let the old port linger: a known port is recorded right away so
+    // `backend_base_url` reflects it; an ephemeral one appears once the
+    // backend writes it back.
+    let written = match port {
+        Some(port) => std::fs::write(port_file(), port.to_string()),
+        None => std::fs::remove_file(port_file()).or_else(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Ok(()),
+            _ => Err(e),
+        }),
+    };
+    if let Err(e) = written {
+        log::warn!("Failed to update {}: {}", port_file().display(), e);
+    }
+
+    app.state::<AutoRestart>().reset();
+    spawn_backend_async(app).await.map_err(|e| {
+        log::error!("Backend rebind failed: {}", e);
+        set_status(app, e.status());
+        e.message
+    })?;
+
+    // The health check always ends in `Healthy` or `Unhealthy` within its budget.
+    let settled = app.state::<BackendState>();
+    match settled.wait_until(|s| !matches!(s, BackendStatus::Starting { .. })).await {
+        BackendStatus::Healthy { port } => Ok(port),
+        BackendStatus::Unhealthy { reason, .. } => Err(reason),
+        _ => Err("backend was stopped".to_string()),
+    }
+}
+
+/// How long the latest startup spent waiting for the port file, until the
+/// first HTTP response, and until ready, plus the number of probes; reset on
+/// every spawn.
+#[tauri::command]
+fn startup_metrics(metrics: State<'_, health::StartupMetrics>) -> health::StartupTimes {
+    metrics.get()
+}
+
+/// Have the backend re-read its config without restarting, via its
+/// `reload_path` endpoint or SIGHUP (see `reload_sighup`). An `unsupported`
+/// error means it offers neither, as opposed to a reload that failed.
+#[tauri::command]
+async fn reload_backend_config(app: AppHandle) -> Result<sidecar::ReloadMethod, CommandError> {
+    sidecar::reload_config(&app).await
+}
+
+/// Write `data` to the backend's stdin as is (include the newline if the
+/// backend reads lines), for backends that take commands that way. A
+/// backend that stops reading fills the pipe, so the write gives up after
+/// [`STDIN_WRITE_TIMEOUT`].
+#[tauri::command]
+async fn backend_stdin_write(app: AppHandle, data: String) -> Result<(), CommandError> {
+    let child = app.state::<Sidecars>().backend_child()?;
+    let write = tauri::async_runtime::spawn_blocking(move || child.write(data.as_bytes()));
+    with_timeout("writing to the backend's stdin", STDIN_WRITE_TIMEOUT, async {
+        write
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("failed to write to {} stdin: {}", sidecar_name(), e))
+    })
+    .await
+}
+
+const STDIN_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether the app is in offline mode: the backend is down, so features that
+/// need it should be disabled and a reconnect banner shown. Changes are
+/// announced with `offline-mode`.
+#[tauri::command]
+fn offline_mode(app: AppHandle) -> bool {
+    app.state::<status::OfflineMode>().get()
+}
+
+/// Called by the main webview once it has painted its first frame. The main
+/// window is shown when this and `backend-ready` have both happened.
+#[tauri::command]
+fn app_ready(app: AppHandle) {
+    splash::app_ready(&app);
+}
+
+/// Probe the backend immediately, e.g. from a "Retry now" button: skips the
+/// rest of a running health check's backoff, or starts a new check.
+#[tauri::command]
+fn health_check_now(app: AppHandle) {
+    health::check_now(&app);
+}
+
+/// Resolve with the port once the backend is healthy, straight away if it
+/// already is, so the frontend can `await` it before its first request.
+/// Rejects if the backend turns unhealthy, or with `timed_out` after
+/// `timeout_ms` (by default the startup budget plus some slack). A stop in
+/// between keeps waiting, for whatever starts it again.
+#[tauri::command]
+async fn wait_for_ready(app: AppHandle, timeout_ms: Option<u64>) -> Result<u16, CommandError> {
+    let timeout = timeout_ms.map_or_else(
+        || {
+            let config = app.state::<BrianConfig>();
+            Duration::from_secs(config.health_budget_secs.max(config.cold_start_budget_secs) + 5)
+        },
+        Duration::from_millis,
+    );
+    let state = app.state::<BackendState>();
+    let ready = async {
+        let settled = |s: &BackendStatus| {
+            matches!(s, BackendStatus::Healthy { .. } | BackendStatus::Unhealthy { .. })
+        };
+        match state.wait_until(settled).await {
+            BackendStatus::Healthy { port } => Ok(port),
+            BackendStatus::Unhealthy { reason, .. } => {
+                Err(format!("backend is unhealthy: {}", reason))
+            }
+            _ => Err("backend stopped".to_string()),
+        }
+    };
+    with_timeout("waiting for the backend", timeout, ready).await
+}
+
+/// Turn respawning after a crash on or off for the rest of the session,
+/// e.g. to keep a crashed backend dead for inspection. Doesn't touch the
+/// config file.
+#[tauri::command]
+fn set_auto_restart(enabled: bool, auto_restart: State<'_, AutoRestart>) {
+    log::info!("Auto-restart {}", if enabled { "enabled" } else { "disabled" });
+    auto_restart.set_enabled(enabled);
+}
+
+/// Whether a crashed backend is currently respawned automatically.
+#[tauri::command]
+fn get_auto_restart(auto_restart: State<'_, AutoRestart>) -> bool {
+    auto_restart.enabled()
+}
+
+/// Current backend lifecycle state, for UIs that load after the one-shot
+/// `backend-ready` / `backend-error` events have already fired.
+#[tauri::command]
+fn backend_status(state: State<'_, BackendState>) -> BackendStatus {
+    state.get()
+}
+
+/// The most recent sidecar output lines (up to `limit`, oldest first), so a
+/// log viewer mounted after startup can show the boot history.
+#[tauri::command]
+fn recent_backend_logs(logs: State<'_, RecentLogs>, limit: Option<usize>) -> Vec<LogLine> {
+    logs.recent(limit)
+}
+
+/// Read up to `max_bytes` of `backend.log` from byte `offset`. Poll with the
+/// returned `next_offset` to tail the file; if it was rotated in between, the
+/// read restarts from the top and `reset` is set.
+#[tauri::command]
+async fn read_backend_log(
+    log: State<'_, BackendLog>,
+    offset: u64,
+    max_bytes: usize,
+) -> Result<LogChunk, CommandError> {
+    let path = log.path();
+    blocking_with_timeout("reading the backend log", BLOCKING_TIMEOUT, move || {
+        logs::read_chunk(&path, offset, max_bytes)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))
+    })
+    .await
+}
+
+/// Deadline for commands doing file or process-table work on the blocking
+/// pool.
+const BLOCKING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The version the running backend reports, cached until the next spawn.
+/// `Unknown` when the backend has no `/version` endpoint.
+#[tauri::command]
+async fn backend_version(app: AppHandle) -> Result<version::BackendVersion, CommandError> {
+    with_timeout("fetching the backend version", Duration::from_secs(5), version::fetch(&app)).await
+}
+
+/// What the running backend says it supports: the JSON document at the
+/// first of `capabilities_paths` it serves, or `{}` if none. Cached until
+/// the next spawn.
+#[tauri::command]
+async fn backend_capabilities(app: AppHandle) -> Result<serde_json::Value, CommandError> {
+    with_timeout(
+        "fetching the backend capabilities",
+        Duration::from_secs(5),
+        capabilities::fetch(&app),
+    )
+    .await
+}
+
+/// The backend's disk space as of the last poll; `None` if it has no
+/// storage endpoint. `backend-storage-warning` reports low space unasked.
+#[tauri::command]
+async fn backend_storage(app: AppHandle) -> Result<Option<storage::StorageStats>, CommandError> {
+    with_timeout("fetching backend storage stats", Duration::from_secs(5), storage::latest(&app))
+        .await
+}
+
+/// Scan `range` (inclusive; `discover_ports` from the config by default)
+/// for a port our backend answers its health check on, for when the port
+/// file is lost. Returns the first one found, without switching to it.
+#[tauri::command]
+async fn discover_backend(
+    app: AppHandle,
+    range: Option<(u16, u16)>,
+) -> Result<Option<u16>, CommandError> {
+    with_timeout("looking for the backend", Duration::from_secs(30), health::discover(&app, range))
+        .await
+}
+
+/// Make a request to the backend on the webview's behalf and return the
+/// response, sidestepping CORS and keeping the transport in Rust.
+#[tauri::command]
+async fn backend_request(
+    app: AppHandle,
+    method: String,
+    path: String,
+    headers: Option<HashMap<String, String>>,
+    body: Option<String>,
+) -> Result<proxy::ProxyResponse, CommandError> {
+    let send = proxy::send(&app, &method, &path, headers.unwrap_or_default(), body);
+    with_timeout(&format!("waiting for {} {}", method, path), proxy::TIMEOUT, send).await
+}
+
+/// Running processes that look like our sidecar, including strays left by
+/// crashes, with the profile and port their PID file records.
+#[tauri::command]
+async fn list_backend_processes(
+    app: AppHandle,
+) -> Result<Vec<processes::BackendProcess>, CommandError> {
+    blocking_with_timeout("listing backend processes", BLOCKING_TIMEOUT, move || {
+        Ok(processes::list(&app))
+    })
+    .await
+}
+
+/// Kill a stray sidecar process by `pid`. Refuses anything that isn't one,
+/// and the sidecar currently in use.
+#[tauri::command]
+async fn kill_backend_process(app: AppHandle, pid: u32) -> Result<(), CommandError> {
+    blocking_with_timeout("killing a backend process", BLOCKING_TIMEOUT, move || {
+        processes::kill(&app, pid)
+    })
+    .await
+}
+
+/// Close the main window after `backend-busy-on-close`, once the user has
+/// agreed to interrupt the backend's work.
+#[tauri::command]
+fn confirm_close(app: AppHandle) {
+    log::info!("Close confirmed despite backend work in progress");
+    close::destroy_main(&app);
+}
+
+/// Relay the backend's Server-Sent Events at `path` (e.g. `/events`) as
+/// `backend-sse` events `{path, event, data, id}`, reconnecting whenever the
+/// stream drops, until `unsubscribe_backend_events`.
+#[tauri::command]
+fn subscribe_backend_events(app: AppHandle, path: String) -> Result<(), String> {
+    sse::subscribe(&app, path)
+}
+
+/// Stop relaying `path`. Returns whether it was subscribed.
+#[tauri::command]
+fn unsubscribe_backend_events(app: AppHandle, path: String) -> bool {
+    sse::unsubscribe(&app, &path)
+}
+
+/// Zip `backend.log` and its rotated predecessors into `dest_path`, e.g. a
+/// `.zip` path picked with a save dialog. Returns the bytes written.
+#[tauri::command]
+async fn export_backend_log(app: AppHandle, dest_path: String) -> Result<u64, CommandError> {
+    let files = app.state::<BackendLog>().files();
+    let dest = PathBuf::from(dest_path);
+    blocking_with_timeout("exporting the backend log", EXPORT_TIMEOUT, move || {
+        logs::export(&files, &dest)
+            .map_err(|e| format!("failed to export to {}: {}", dest.display(), e))
+    })
+    .await
+}
+
+/// Compressing a full set of rotated logs takes a while on a slow disk.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Start `backend.log` afresh, e.g. before reproducing a bug, and return
+/// where the closed file went. With `clear_buffer`, the in-memory lines are
+/// cleared too and returned.
+#[tauri::command]
+async fn rotate_backend_log(
+    app: AppHandle,
+    clear_buffer: Option<bool>,
+) -> Result<RotatedLog, CommandError> {
+    blocking_with_timeout("rotating the backend log", BLOCKING_TIMEOUT, move || {
+        let log = app.state::<BackendLog>();
+        let path = log
+            .rotate_now()
+            .map_err(|e| format!("failed to rotate {}: {}", log.path().display(), e))?;
+        log::info!("Rotated backend log on request");
+        let buffer = clear_buffer.unwrap_or(false).then(|| app.state::<RecentLogs>().take());
+        Ok(RotatedLog {
+            path: path.map(|path| path.display().to_string()),
+            buffer,
+        })
+    })
+    .await
+}
+
+/// Set once setup launches the sidecar; stays false in debug builds and with
+/// `--no-sidecar`, where the backend is run by hand.
+static SIDECAR_SPAWNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, serde::Serialize)]
+struct RuntimeInfo {
+    debug: bool,
+    sidecar_spawned: bool,
+    app_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+}
+
+/// How this shell is running, so the UI can tailor guidance, e.g. a "start
+/// the backend manually" hint when no sidecar was spawned.
+#[tauri::command]
+fn runtime_info() -> RuntimeInfo {
+    RuntimeInfo {
+        debug: cfg!(debug_assertions),
+        sidecar_spawned: SIDECAR_SPAWNED.load(Ordering::SeqCst),
+        app_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+    }
+}
+
+/// Bundle version, platform, backend status, port-file details, and recent
+/// sidecar output into one report for bug filing.
+#[tauri::command]
+async fn collect_diagnostics(app: AppHandle) -> Result<diagnostics::Diagnostics, CommandError> {
+    blocking_with_timeout("collecting diagnostics", BLOCKING_TIMEOUT, move || {
+        Ok(diagnostics::collect(&app))
+    })
+    .await
+}
+
+/// Open the active profile's state dir (logs, port file, status) in the
+/// platform file manager, creating it first if needed.
+#[tauri::command]
+async fn open_state_dir(app: AppHandle) -> Result<(), CommandError> {
+    use tauri_plugin_opener::OpenerExt;
+
+    blocking_with_timeout("opening the state dir", BLOCKING_TIMEOUT, move || {
+        let dir = state_dir();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        app.opener()
+            .open_path(dir.display().to_string(), None::<&str>)
+            .map_err(|e| format!("failed to open {}: {}", dir.display(), e))
+    })
+    .await
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    APP_STARTED.get_or_init(SystemTime::now);
+
+    let log_spec = std::env::var("RUST_LOG")
+        .ok()
+        .or_else(|| BrianConfig::peek_log_level(&brian_dir().join("config.toml")))
+        .unwrap_or_default();
+    let (log_level, log_targets) = config::parse_log_spec(&log_spec);
+    let mut log_plugin = tauri_plugin_log::Builder::default().level(log_level);
+    for (target, level) in log_targets {
+        log_plugin = log_plugin.level_for(target, level);
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .plugin(
-            tauri_plugin_log::Builder::default()
-                .level(log::LevelFilter::Info)
-                .build(),
-        )
-        .manage(SidecarChild(Mutex::new(None)))
+        .plugin(tauri_plugin_opener::init())
+        .plugin(log_plugin.build())
+        .manage(Sidecars(Mutex::new(Vec::new())))
+        .manage(ShuttingDown(AtomicBool::new(false)))
+        .manage(RequestedPort(Mutex::new(None)))
+        .manage(PortOverride(Mutex::new(None)))
+        .manage(StdoutPort(Mutex::new(None)))
+        .manage(sidecar::AuthToken::default())
+        .manage(version::VersionCache::default())
+        .manage(capabilities::CapabilitiesCache::default())
+        .manage(storage::StorageState::default())
+        .manage(health::StartupMetrics::default())
+        .manage(health::ProbeNow::default())
+        .manage(splash::RevealGate::default())
+        .manage(sse::SseSubscriptions::default())
+        .manage(Restarts::default())
+        .manage(mock::MockBackend::default())
+        .manage(BackendState::new(BackendStatus::LAUNCHING))
+        .manage(status::OfflineMode::default())
+        .manage(status::StatusHistory::default())
+        .invoke_handler(tauri::generate_handler![
+            get_backend_port,
+            backend_base_url,
+            restart_backend,
+            stop_backend,
+            rebind_backend,
+            wait_for_ready,
+            startup_metrics,
+            app_ready,
+            health_check_now,
+            reload_backend_config,
+            relaunch_app,
+            backend_stdin_write,
+            offline_mode,
+            set_backend_port,
+            set_mock_backend_status,
+            clear_backend_port_override,
+            set_auto_restart,
+            get_auto_restart,
+            backend_status,
+            recent_backend_logs,
+            read_backend_log,
+            export_backend_log,
+            rotate_backend_log,
+            collect_diagnostics,
+            runtime_info,
+            list_backend_processes,
+            kill_backend_process,
+            confirm_close,
+            open_state_dir,
+            backend_version,
+            backend_capabilities,
+            backend_storage,
+            discover_backend,
+            backend_request,
+            subscribe_backend_events,
+            unsubscribe_backend_events
+        ])
         .setup(|app| {
+            let config = BrianConfig::load(&brian_dir().join("config.toml"));
+            init_profile(&config);
+            let Some(lock) = instance::acquire() else {
+                log::info!("Brian is already running for this profile, handing over to it");
+                instance::forward_to_running();
+                std::process::exit(0);
+            };
+            app.manage(lock);
+            instance::watch_activations(app.handle().clone());
+            sidecar::init_sidecar_name(&config);
+            app.manage(AutoRestart::new(&config));
+            app.manage(BackendLog(Mutex::new(RotatingLog::new(
+                &state_dir().join("logs"),
+                config.log_max_bytes,
+                config.log_keep_files,
+            ))));
+            app.manage(RecentLogs::new(config.log_buffer_lines));
+            app.manage(LogRate::new(&config));
+            app.manage(redact::Redactor::new(&config.redact_patterns));
+            app.manage(proxy::BackendClient(http_client(&config).build().unwrap_or_default()));
+            if config.danger_accept_invalid_certs {
+                log::warn!("danger_accept_invalid_certs is enabled: backend TLS certificates are NOT verified");
+            }
+            app.manage(config);
+            log::info!("Using port file {}", port_file().display());
+            port_watch::spawn(app.handle().clone());
+            wake::spawn(app.handle().clone());
+            metrics::spawn(app.handle().clone());
+            tray::create(app.handle())?;
+            // Replace whatever a previous run left behind.
+            status::write_status_file(app.handle(), &app.state::<BackendState>().get());
+
+            // ── Without a home there's no telling where the backend's state is ──
+            if resolve_brian_dir().is_none() {
+                log::error!(
+                    "Can't resolve the home directory and BRIAN_HOME isn't set; \
+                     not starting {}",
+                    sidecar_name()
+                );
+                set_status(
+                    app.handle(),
+                    BackendStatus::unhealthy(
+                        ErrorKind::NoHomeDir,
+                        "can't find your home directory to keep Brian's data in",
+                        Some("set BRIAN_HOME to a writable directory".to_string()),
+                    ),
+                );
+                splash::create(app.handle())?;
+                return Ok(());
+            }
+
+            // ── Or fake one entirely, for UI work ──
+            if mock::start_from_env(app.handle()) {
+                splash::reveal_main(app.handle());
+                return Ok(());
+            }
+
             // ── In debug mode, skip sidecar spawn (dev runs backend manually) ──
-            if cfg!(debug_assertions) {
+            // Script mode (`backend_interpreter`) spawns it even then.
+            let script_mode = app.state::<BrianConfig>().backend_interpreter.is_some();
+            if cfg!(debug_assertions) && !script_mode {
+                splash::reveal_main(app.handle());
                 log::info!(
                     "Brian desktop app started (debug) — run backend manually: \
-                     cd brian && python -m brian.main"
+                     cd brian && python -m brian.main (or set backend_interpreter)"
                 );
                 return Ok(());
             }
 
-            // ── Spawn the Python backend sidecar ──
-            log::info!("Spawning brian-backend sidecar…");
-
-            let sidecar_cmd = app
-                .shell()
-                .sidecar("brian-backend")
-                .expect("failed to create brian-backend sidecar command");
-
-            let (mut rx, child) = sidecar_cmd.spawn().expect("failed to spawn brian-backend sidecar");
-
-            // Store the child handle in managed state for cleanup.
-            {
-                let state = app.state::<SidecarChild>();
-                let mut guard = state.0.lock().expect("sidecar state lock poisoned");
-                *guard = Some(child);
+            // ── Or attach to one started by hand, e.g. for profiling ──
+            if std::env::args().any(|arg| arg == "--no-sidecar") {
+                log::warn!(
+                    "--no-sidecar given: not spawning {}, attaching to the backend on the port file",
+                    sidecar_name()
+                );
+                splash::create(app.handle())?;
+                health::spawn_health_check(app.handle().clone(), sidecar::ATTACHED);
+                return Ok(());
             }
 
-            log::info!("brian-backend sidecar spawned, streaming output…");
-
-            // ── Stream sidecar stdout / stderr to the app log ──
-            let log_handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                while let Some(event) = rx.recv().await {
-                    match event {
-                        CommandEvent::Stdout(line) => {
-                            let text = String::from_utf8_lossy(&line);
-                            log::info!("[brian-backend] {}", text);
-                        }
-                        CommandEvent::Stderr(line) => {
-                            let text = String::from_utf8_lossy(&line);
-                            log::error!("[brian-backend] {}", text);
-                        }
-                        CommandEvent::Terminated(status) => {
-                            log::warn!(
-                                "[brian-backend] process terminated with status: {:?}",
-                                status
-                            );
-                            let _ = log_handle.emit("backend-error", "sidecar process terminated unexpectedly");
-                            break;
-                        }
-                        CommandEvent::Error(err) => {
-                            log::error!("[brian-backend] error: {}", err);
-                        }
-                        _ => {}
-                    }
-                }
-            });
-
-            // ── Health-check: poll /health until the backend is ready ──
-            // Wait a moment for the sidecar to write the port file, then read it.
-            let health_handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                const MAX_RETRIES: u32 = 30;
-                const RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
-                let client = reqwest::Client::new();
-
-                // Give the sidecar a moment to start and write the port file
-                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-
-                let mut port = read_backend_port();
-                log::info!("Health checking backend on port {} (from port file)…", port);
-
-                for attempt in 1..=MAX_RETRIES {
-                    // Re-read port file each attempt — sidecar may update it after startup
-                    let current_port = read_backend_port();
-                    if current_port != port {
-                        log::info!("Port file updated: {} → {}", port, current_port);
-                        port = current_port;
-                    }
-
-                    let url = format!("http://127.0.0.1:{}/health", port);
-                    log::info!("Health check attempt {}/{} on port {}…", attempt, MAX_RETRIES, port);
-
-                    match client.get(&url).send().await {
-                        Ok(resp) if resp.status().is_success() => {
-                            log::info!("brian-backend is healthy on port {} (attempt {})", port, attempt);
-                            let _ = health_handle.emit("backend-ready", port);
-                            return;
-                        }
-                        Ok(resp) => {
-                            log::warn!(
-                                "Health check returned non-success status: {}",
-                                resp.status()
-                            );
-                        }
-                        Err(e) => {
-                            log::warn!("Health check failed: {}", e);
-                        }
-                    }
-                    tokio::time::sleep(RETRY_DELAY).await;
-                }
-
-                log::error!(
-                    "brian-backend did not become healthy after {} attempts",
-                    MAX_RETRIES
-                );
-                let _ = health_handle.emit(
-                    "backend-error",
-                    "backend health check failed after 30 retries",
-                );
-            });
+            // ── Spawn the Python backend sidecar behind a splash ──
+            splash::create(app.handle())?;
+            sidecar::reap_stale_sidecar();
+            SIDECAR_SPAWNED.store(true, Ordering::SeqCst);
+            sidecar::spawn_backend_with_retries(app.handle().clone());
 
             Ok(())
         })
-        // ── Kill sidecar on window close ──
-        .on_window_event(|window, event| {
-            if let tauri::WindowEvent::Destroyed = event {
-                let state = window.state::<SidecarChild>();
-                let mut guard = state.0.lock().expect("sidecar state lock poisoned");
-                if let Some(child) = guard.take() {
-                    log::info!("Killing brian-backend sidecar on window destroy…");
-                    let _ = child.kill();
-                }
+        // ── Shut the sidecar down on main window close ──
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::CloseRequested { api, .. } if window.label() == "main" => {
+                close::on_close_requested(window, api);
             }
+            tauri::WindowEvent::Destroyed => match window.label() {
+                "main" => {
+                    log::info!("Window destroyed, stopping {} sidecar…", sidecar_name());
+                    window.state::<ShuttingDown>().0.store(true, Ordering::SeqCst);
+                    sidecar::shutdown_all(window.app_handle());
+                    window.state::<instance::InstanceLock>().release();
+                }
+                // Closing the splash early mustn't leave the app running invisibly.
+                splash::LABEL => splash::reveal_main(window.app_handle()),
+                _ => {}
+            },
+            _ => {}
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
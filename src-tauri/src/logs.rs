@@ -0,0 +1,597 @@
+//! Sidecar output handling: each line goes to the app log, to the on-disk
+//! `logs/backend.log` in the state dir (rolled over by size so users have a single,
+//! bounded file to attach to bug reports), to the webview as `backend-log`,
+//! and to an in-memory ring buffer so a log viewer opened late still sees the
+//! boot output.
+
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::time::Instant;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::config::BrianConfig;
+use crate::redact::Redactor;
+use crate::sidecar::sidecar_name;
+
+/// One line of sidecar output, as delivered to the webview.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct LogLine {
+    /// `"stdout"` or `"stderr"`.
+    pub stream: &'static str,
+    pub line: String,
+    /// Milliseconds since the Unix epoch.
+    pub ts: u64,
+}
+
+/// Lines waiting to go out as one `backend-log` event. Lines arriving within
+/// [`LogBatch::WINDOW`] of the first are coalesced, so a verbose startup
+/// doesn't flood the IPC channel with one event per line.
+#[derive(Default)]
+pub(crate) struct LogBatch {
+    lines: Vec<LogLine>,
+    deadline: Option<Instant>,
+}
+
+impl LogBatch {
+    const WINDOW: Duration = Duration::from_millis(50);
+
+    fn push(&mut self, line: LogLine) {
+        self.deadline.get_or_insert_with(|| Instant::now() + Self::WINDOW);
+        self.lines.push(line);
+    }
+
+    /// Resolves when the pending batch should be sent; never, if it's empty.
+    pub async fn due(&self) {
+        match self.deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Emit pending lines as a single `backend-log` event carrying a `LogLine[]`.
+    pub fn flush(&mut self, app: &AppHandle) {
+        self.deadline = None;
+        if !self.lines.is_empty() {
+            let _ = app.emit("backend-log", std::mem::take(&mut self.lines));
+        }
+    }
+}
+
+/// One line of raw sidecar output as text: invalid UTF-8 replaced, and cut
+/// short at `log_max_line_bytes`.
+pub(crate) fn decode_line<'a>(app: &AppHandle, raw: &'a [u8]) -> Cow<'a, str> {
+    let (raw, truncated) = app.state::<LogRate>().truncate(raw);
+    let mut text = String::from_utf8_lossy(raw);
+    if truncated {
+        text.to_mut().push_str("…[truncated]");
+    }
+    text
+}
+
+/// Fan one line of raw sidecar output out to every sink, with secrets
+/// masked. During a log flood most lines skip the sinks (see [`LogRate`]).
+/// Returns the line as recorded.
+pub(crate) fn record_line(
+    app: &AppHandle,
+    batch: &mut LogBatch,
+    stream: &'static str,
+    raw: &[u8],
+) -> String {
+    let text = decode_line(app, raw);
+    let text = app.state::<Redactor>().redact(&text).into_owned();
+    if !app.state::<LogRate>().record(app, text.len()) {
+        return text;
+    }
+    if stream == "stderr" {
+        log::error!("[{}] {}", sidecar_name(), text);
+    } else {
+        log::info!("[{}] {}", sidecar_name(), text);
+    }
+    app.state::<BackendLog>().write_line(stream, &text);
+
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    let line = LogLine {
+        stream,
+        line: text.trim_end().to_string(),
+        ts,
+    };
+    app.state::<RecentLogs>().push(line.clone());
+    batch.push(line);
+    text
+}
+
+/// Counters kept by [`LogRate`], as reported in diagnostics.
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct LogRateStats {
+    pub lines_total: u64,
+    pub bytes_total: u64,
+    /// Lines that skipped the sinks because of a flood.
+    pub lines_skipped: u64,
+    /// Lines cut short at `log_max_line_bytes`.
+    pub lines_truncated: u64,
+    /// Most lines and bytes seen in any one-second window.
+    pub peak_lines_per_sec: u64,
+    pub peak_bytes_per_sec: u64,
+    /// Whether the current window is over the threshold.
+    pub flooding: bool,
+}
+
+/// Payload of `backend-log-flood`.
+#[derive(Debug, Clone, Serialize)]
+struct LogFlood {
+    lines_per_sec: u64,
+    bytes_per_sec: u64,
+    /// Only one line in this many reaches the sinks until the flood ends.
+    sample_every: u64,
+}
+
+#[derive(Default)]
+struct RateWindow {
+    started: Option<Instant>,
+    lines: u64,
+    bytes: u64,
+    stats: LogRateStats,
+}
+
+/// Managed rolling count of sidecar output per second, so a backend stuck
+/// in a logging loop gets sampled instead of filling the disk and freezing
+/// the log viewer. Also caps the length of each line.
+pub(crate) struct LogRate {
+    max_lines: u64,
+    max_bytes: u64,
+    max_line_bytes: usize,
+    window: Mutex<RateWindow>,
+}
+
+impl LogRate {
+    const WINDOW: Duration = Duration::from_secs(1);
+    const SAMPLE_EVERY: u64 = 100;
+
+    pub fn new(config: &BrianConfig) -> Self {
+        Self {
+            max_lines: config.log_flood_lines_per_sec,
+            max_bytes: config.log_flood_bytes_per_sec,
+            max_line_bytes: config.log_max_line_bytes,
+            window: Mutex::new(RateWindow::default()),
+        }
+    }
+
+    /// `raw` cut to at most `log_max_line_bytes`, backing off to the start
+    /// of a UTF-8 character, and whether anything was cut.
+    fn truncate<'a>(&self, raw: &'a [u8]) -> (&'a [u8], bool) {
+        if raw.len() <= self.max_line_bytes {
+            return (raw, false);
+        }
+        let mut end = self.max_line_bytes;
+        while end > 0 && raw[end] & 0xC0 == 0x80 {
+            end -= 1;
+        }
+        self.window.lock().unwrap_or_else(PoisonError::into_inner).stats.lines_truncated += 1;
+        (&raw[..end], true)
+    }
+
+    /// Count a line of `bytes` bytes; returns whether it should reach the sinks.
+    fn record(&self, app: &AppHandle, bytes: usize) -> bool {
+        let now = Instant::now();
+        let mut window = self.window.lock().unwrap_or_else(PoisonError::into_inner);
+        if !window.started.is_some_and(|started| now - started < Self::WINDOW) {
+            if window.stats.flooding && !self.exceeded(&window) {
+                window.stats.flooding = false;
+                log::warn!(
+                    "{} log flood over; {} lines skipped so far",
+                    sidecar_name(),
+                    window.stats.lines_skipped
+                );
+            }
+            window.started = Some(now);
+            window.lines = 0;
+            window.bytes = 0;
+        }
+        window.lines += 1;
+        window.bytes += bytes as u64;
+        window.stats.lines_total += 1;
+        window.stats.bytes_total += bytes as u64;
+        window.stats.peak_lines_per_sec = window.stats.peak_lines_per_sec.max(window.lines);
+        window.stats.peak_bytes_per_sec = window.stats.peak_bytes_per_sec.max(window.bytes);
+
+        if !window.stats.flooding && self.exceeded(&window) {
+            window.stats.flooding = true;
+            let flood = LogFlood {
+                lines_per_sec: window.lines,
+                bytes_per_sec: window.bytes,
+                sample_every: Self::SAMPLE_EVERY,
+            };
+            drop(window);
+            log::warn!(
+                "{} is flooding its log ({} lines, {} bytes in under a second); keeping 1 in {}",
+                sidecar_name(),
+                flood.lines_per_sec,
+                flood.bytes_per_sec,
+                flood.sample_every
+            );
+            let _ = app.emit("backend-log-flood", flood);
+            return true;
+        }
+        if window.stats.flooding && window.lines % Self::SAMPLE_EVERY != 0 {
+            window.stats.lines_skipped += 1;
+            return false;
+        }
+        true
+    }
+
+    fn exceeded(&self, window: &RateWindow) -> bool {
+        (self.max_lines > 0 && window.lines > self.max_lines)
+            || (self.max_bytes > 0 && window.bytes > self.max_bytes)
+    }
+
+    pub fn stats(&self) -> LogRateStats {
+        self.window.lock().unwrap_or_else(PoisonError::into_inner).stats.clone()
+    }
+}
+
+/// Managed ring buffer of the most recent sidecar output lines.
+pub(crate) struct RecentLogs {
+    lines: Mutex<VecDeque<LogLine>>,
+    capacity: usize,
+}
+
+impl RecentLogs {
+    /// Longest line kept, in bytes; anything past it is cut off so one runaway
+    /// line can't pin megabytes of memory.
+    const MAX_LINE_BYTES: usize = 4096;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    fn push(&self, mut line: LogLine) {
+        if self.capacity == 0 {
+            return;
+        }
+        if line.line.len() > Self::MAX_LINE_BYTES {
+            let mut end = Self::MAX_LINE_BYTES;
+            while !line.line.is_char_boundary(end) {
+                end -= 1;
+            }
+            line.line.truncate(end);
+            line.line.push('…');
+        }
+        let mut lines = self.lines.lock().unwrap_or_else(PoisonError::into_inner);
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Empty the buffer, returning what it held, oldest first.
+    pub fn take(&self) -> Vec<LogLine> {
+        let mut lines = self.lines.lock().unwrap_or_else(PoisonError::into_inner);
+        lines.drain(..).collect()
+    }
+
+    /// The last `limit` lines (all of them if `None`), oldest first.
+    pub fn recent(&self, limit: Option<usize>) -> Vec<LogLine> {
+        let lines = self.lines.lock().unwrap_or_else(PoisonError::into_inner);
+        let skip = lines.len().saturating_sub(limit.unwrap_or(usize::MAX));
+        lines.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Appends timestamped sidecar output to `backend.log`, renaming it to
+/// `backend.log.1` (shifting older files up) once it exceeds `max_bytes`,
+/// and keeping at most `keep` rotated files.
+pub(crate) struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: Option<File>,
+    written: u64,
+    /// Set after a failed write so a broken disk logs one warning, not one per line.
+    failing: bool,
+}
+
+impl RotatingLog {
+    pub fn new(dir: &Path, max_bytes: u64, keep: usize) -> Self {
+        Self {
+            path: dir.join("backend.log"),
+            max_bytes,
+            keep,
+            file: None,
+            written: 0,
+            failing: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one line tagged with its stream (`stdout` / `stderr`). Failures
+    /// are logged and swallowed so they never interrupt log streaming.
+    pub fn write_line(&mut self, stream: &str, line: &str) {
+        match self.try_write_line(stream, line) {
+            Ok(()) => self.failing = false,
+            Err(e) => {
+                if !self.failing {
+                    log::warn!("Failed to write {}: {}", self.path.display(), e);
+                }
+                self.failing = true;
+                // Reopen on the next line in case the file was removed underneath us.
+                self.file = None;
+            }
+        }
+    }
+
+    fn try_write_line(&mut self, stream: &str, line: &str) -> io::Result<()> {
+        if self.written >= self.max_bytes {
+            if let Err(e) = self.rotate() {
+                log::warn!("Failed to rotate {}: {}, appending to it", self.path.display(), e);
+            }
+        }
+        let entry = format!(
+            "{} [{}] {}\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            stream,
+            line.trim_end()
+        );
+        self.file()?.write_all(entry.as_bytes())?;
+        self.written += entry.len() as u64;
+        Ok(())
+    }
+
+    fn file(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+            self.written = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().expect("log file just opened"))
+    }
+
+    /// The current file and its rotated predecessors that exist, oldest first.
+    pub fn files(&self) -> Vec<PathBuf> {
+        (1..=self.keep)
+            .rev()
+            .map(|n| self.rotated(n))
+            .chain(std::iter::once(self.path.clone()))
+            .filter(|path| path.exists())
+            .collect()
+    }
+
+    /// Path of the `n`th rotated file (`backend.log.n`).
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    /// Roll over now rather than at `max_bytes`, so the next line starts a
+    /// fresh file. Returns where the closed file went: `backend.log.1`, or
+    /// `None` if there was nothing to close or `keep` is 0 (deleted).
+    pub fn rotate_now(&mut self) -> io::Result<Option<PathBuf>> {
+        if let Some(file) = &mut self.file {
+            file.flush()?;
+        }
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        self.rotate()?;
+        Ok((self.keep > 0).then(|| self.rotated(1)))
+    }
+
+    /// Shift the files down one, dropping the oldest, and reopen `path`
+    /// either way: a failed shift costs the size limit, not the lines, and
+    /// is retried once another `max_bytes` has been written. A file that's
+    /// already gone (deleted underneath us) counts as moved.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        let shifted = self.shift();
+        self.file()?;
+        self.written = 0;
+        shifted
+    }
+
+    fn shift(&self) -> io::Result<()> {
+        if self.keep == 0 {
+            return ignore_missing(std::fs::remove_file(&self.path));
+        }
+        ignore_missing(std::fs::remove_file(self.rotated(self.keep)))?;
+        for n in (1..self.keep).rev() {
+            ignore_missing(std::fs::rename(self.rotated(n), self.rotated(n + 1)))?;
+        }
+        ignore_missing(std::fs::rename(&self.path, self.rotated(1)))
+    }
+}
+
+fn ignore_missing(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Managed state wrapping the shared [`RotatingLog`]; one file spans sidecar restarts.
+pub(crate) struct BackendLog(pub Mutex<RotatingLog>);
+
+impl BackendLog {
+    pub fn write_line(&self, stream: &str, line: &str) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write_line(stream, line);
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).path().to_path_buf()
+    }
+
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).files()
+    }
+
+    pub fn rotate_now(&self) -> io::Result<Option<PathBuf>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).rotate_now()
+    }
+}
+
+/// Zip `files` into one shareable archive at `dest`, an entry per file
+/// under its own name, after an `export.txt` naming the app version,
+/// platform and export time. Streams each file, so logs of any size are
+/// fine. Returns the archive's size.
+pub(crate) fn export(files: &[PathBuf], dest: &Path) -> io::Result<u64> {
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    let mut zip = ZipWriter::new(io::BufWriter::new(File::create(dest)?));
+    zip.start_file("export.txt", options)?;
+    writeln!(
+        zip,
+        "Brian {} backend log ({} {}), exported {}",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        chrono::Local::now().to_rfc3339()
+    )?;
+    for path in files {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            // Rotated away since the list was taken.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+        zip.start_file(name, options)?;
+        io::copy(&mut file, &mut zip)?;
+    }
+    let mut out = zip.finish()?;
+    out.flush()?;
+    Ok(out.get_ref().metadata()?.len())
+}
+
+/// What `rotate_backend_log` closed off.
+#[derive(Debug, Serialize)]
+pub(crate) struct RotatedLog {
+    /// Where the closed file now is; `None` if there was none to close or
+    /// `log_keep_files` is 0.
+    pub path: Option<String>,
+    /// The in-memory lines, if the buffer was cleared too.
+    pub buffer: Option<Vec<LogLine>>,
+}
+
+/// A slice of `backend.log`, as returned by `read_backend_log`.
+#[derive(Debug, Serialize)]
+pub(crate) struct LogChunk {
+    /// The text read; always whole UTF-8 characters.
+    pub bytes: String,
+    /// Where to continue reading from on the next call.
+    pub next_offset: u64,
+    /// Whether the read reached the current end of the file.
+    pub eof: bool,
+    /// Set when `offset` was past the end — the file was rotated or
+    /// truncated — and reading restarted from the top.
+    pub reset: bool,
+}
+
+/// Read up to `max_bytes` of the log at `path` starting at byte `offset`.
+/// A missing file reads as empty.
+pub(crate) fn read_chunk(path: &Path, offset: u64, max_bytes: usize) -> io::Result<LogChunk> {
+    /// Upper bound on a single read, whatever the caller asks for.
+    const MAX_CHUNK: usize = 1024 * 1024;
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(LogChunk {
+                bytes: String::new(),
+                next_offset: 0,
+                eof: true,
+                reset: offset > 0,
+            });
+        }
+        Err(e) => return Err(e),
+    };
+    let len = file.metadata()?.len();
+    let reset = offset > len;
+    let offset = if reset { 0 } else { offset };
+
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::new();
+    file.take(max_bytes.min(MAX_CHUNK) as u64).read_to_end(&mut buf)?;
+    // Don't split a multi-byte character across chunks; the tail is re-read next time.
+    let valid = match std::str::from_utf8(&buf) {
+        Ok(_) => buf.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => buf.len(),
+    };
+    let next_offset = offset + valid as u64;
+    Ok(LogChunk {
+        bytes: String::from_utf8_lossy(&buf[..valid]).into_owned(),
+        next_offset,
+        eof: next_offset >= len,
+        reset,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("brian-logs-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn rotate_survives_files_deleted_underneath() {
+        for keep in [0, 2] {
+            let dir = temp_dir(&format!("deleted-{}", keep));
+            let mut log = RotatingLog::new(&dir, 1, keep);
+            log.write_line("stdout", "first");
+            std::fs::remove_file(log.path()).unwrap();
+
+            log.write_line("stdout", "second");
+
+            let text = std::fs::read_to_string(log.path()).unwrap();
+            let _ = std::fs::remove_dir_all(&dir);
+            assert!(!log.failing, "keep = {}", keep);
+            assert!(text.ends_with("[stdout] second\n"), "keep = {}: {:?}", keep, text);
+        }
+    }
+
+    #[test]
+    fn rotate_shifts_files_down() {
+        let dir = temp_dir("shift");
+        let mut log = RotatingLog::new(&dir, 1, 2);
+        for line in ["one", "two", "three"] {
+            log.write_line("stdout", line);
+        }
+
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        let current = read(log.path.clone());
+        let (first, second) = (read(log.rotated(1)), read(log.rotated(2)));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(current.ends_with("three\n"));
+        assert!(first.ends_with("two\n"));
+        assert!(second.ends_with("one\n"));
+    }
+}
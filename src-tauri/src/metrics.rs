@@ -0,0 +1,150 @@
+//! Opt-in sampling of the sidecar's CPU and memory use, emitted as
+//! `backend-metrics` so the frontend can chart it while hunting leaks, and
+//! checked against `backend_max_rss_mb`.
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::BrianConfig;
+use crate::sidecar::{shutdown_backend, sidecar_name, spawn_backend, Sidecars};
+use crate::status::{set_status, BackendState, BackendStatus};
+
+/// Payload of `backend-metrics`.
+#[derive(Debug, Clone, Serialize)]
+struct BackendMetrics {
+    /// Share of one core, so a busy multi-threaded backend can exceed 100.
+    cpu_pct: f32,
+    rss_bytes: u64,
+}
+
+/// Payload of `backend-memory-exceeded`.
+#[derive(Debug, Clone, Serialize)]
+struct MemoryExceeded {
+    rss_bytes: u64,
+    limit_bytes: u64,
+    /// How long it has been over the limit.
+    over_secs: u64,
+    /// Whether the backend is being restarted for it.
+    restarting: bool,
+}
+
+/// Tracks how long the sidecar has been over `backend_max_rss_mb`.
+struct MemoryLimit {
+    limit_bytes: u64,
+    sustain: Duration,
+    enforce: bool,
+    /// The PID over the limit and since when; a new process starts afresh.
+    over_since: Option<(u32, Instant)>,
+}
+
+impl MemoryLimit {
+    fn new(config: &BrianConfig) -> Option<Self> {
+        Some(Self {
+            limit_bytes: config.backend_max_rss_mb? * 1024 * 1024,
+            sustain: Duration::from_secs(config.backend_max_rss_secs),
+            enforce: config.backend_max_rss_enforce,
+            over_since: None,
+        })
+    }
+
+    /// Account for a sample of `rss_bytes` from `pid`, acting once it has been
+    /// over the limit for the whole sustain window.
+    fn check(&mut self, app: &AppHandle, pid: u32, rss_bytes: u64) {
+        if rss_bytes <= self.limit_bytes {
+            self.over_since = None;
+            return;
+        }
+        let since = match self.over_since {
+            Some((over_pid, since)) if over_pid == pid => since,
+            _ => self.over_since.insert((pid, Instant::now())).1,
+        };
+        if since.elapsed() < self.sustain {
+            return;
+        }
+        // Start the window over, so a still-bloated backend is reported
+        // again a window later rather than on every sample.
+        self.over_since = None;
+        log::warn!(
+            "{} (pid {}) has used {} MB for over {}s, above the {} MB limit{}",
+            sidecar_name(),
+            pid,
+            rss_bytes / (1024 * 1024),
+            self.sustain.as_secs(),
+            self.limit_bytes / (1024 * 1024),
+            if self.enforce { "; restarting it" } else { "" }
+        );
+        let _ = app.emit(
+            "backend-memory-exceeded",
+            MemoryExceeded {
+                rss_bytes,
+                limit_bytes: self.limit_bytes,
+                over_secs: since.elapsed().as_secs(),
+                restarting: self.enforce,
+            },
+        );
+        if self.enforce {
+            // Stopped gracefully so it can flush its state, and without the
+            // fresh auto-restart budget a manual restart would grant.
+            let app = app.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                shutdown_backend(&app);
+                if let Err(e) = spawn_backend(&app) {
+                    log::error!("Restart for exceeding the memory limit failed: {}", e);
+                    set_status(&app, e.status());
+                }
+            });
+        }
+    }
+}
+
+/// Sample the sidecar every `metrics_interval_secs` for the app's lifetime,
+/// if `metrics` is enabled or there's a `backend_max_rss_mb` to enforce.
+/// Nothing is sampled while the backend is stopped or between spawns.
+pub(crate) fn spawn(app: AppHandle) {
+    let config = app.state::<BrianConfig>();
+    let emit = config.metrics;
+    let mut limit = MemoryLimit::new(&config);
+    if !emit && limit.is_none() {
+        return;
+    }
+    let interval = Duration::from_secs(config.metrics_interval_secs.max(1));
+    log::info!("Sampling backend resource usage every {}s", interval.as_secs());
+
+    tauri::async_runtime::spawn(async move {
+        // Kept across samples: CPU usage is measured between two refreshes.
+        let mut system = System::new();
+        loop {
+            tokio::time::sleep(interval).await;
+            if app.state::<BackendState>().get() == BackendStatus::Stopped {
+                continue;
+            }
+            let Some(raw_pid) = app.state::<Sidecars>().pid() else {
+                continue;
+            };
+            let pid = Pid::from_u32(raw_pid);
+            system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&[pid]),
+                true,
+                ProcessRefreshKind::nothing().with_cpu().with_memory(),
+            );
+            let Some(process) = system.process(pid) else {
+                continue;
+            };
+            if emit {
+                let _ = app.emit(
+                    "backend-metrics",
+                    BackendMetrics {
+                        cpu_pct: process.cpu_usage(),
+                        rss_bytes: process.memory(),
+                    },
+                );
+            }
+            if let Some(limit) = &mut limit {
+                limit.check(&app, raw_pid, process.memory());
+            }
+        }
+    });
+}
@@ -0,0 +1,116 @@
+//! A fake backend for UI work (debug builds only): the status machine is
+//! driven by `set_mock_backend_status` or `BRIAN_MOCK_BACKEND` instead of a
+//! real sidecar, and spawning and health checks are suppressed while it's on.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Deserialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::BrianConfig;
+use crate::status::{set_status, BackendStatus, ErrorKind, StartupPhase};
+
+/// Managed flag: whether the mock backend has taken over.
+#[derive(Default)]
+pub(crate) struct MockBackend(AtomicBool);
+
+/// Whether the mock backend is on. Always false in release builds.
+pub(crate) fn active(app: &AppHandle) -> bool {
+    cfg!(debug_assertions) && app.state::<MockBackend>().0.load(Ordering::SeqCst)
+}
+
+/// A status to fake, shaped like `backend-status` payloads, e.g.
+/// `{"state":"healthy","port":8000}`; anything left out gets a placeholder.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub(crate) enum MockStatus {
+    Starting {
+        phase: Option<StartupPhase>,
+    },
+    Healthy {
+        port: Option<u16>,
+    },
+    Unhealthy {
+        kind: Option<ErrorKind>,
+        reason: Option<String>,
+        detail: Option<String>,
+    },
+    /// `Unhealthy` with `kind: port_conflict`, plus its `backend-port-conflict`.
+    PortConflict {
+        port: Option<u16>,
+    },
+    Stopped,
+}
+
+/// Switch to the mock backend with `BRIAN_MOCK_BACKEND` (a state name like
+/// `healthy`, or a full [`MockStatus`] as JSON), if it's set. Returns whether
+/// it was.
+pub(crate) fn start_from_env(app: &AppHandle) -> bool {
+    if !cfg!(debug_assertions) {
+        return false;
+    }
+    let Ok(value) = std::env::var("BRIAN_MOCK_BACKEND") else {
+        return false;
+    };
+    let json = if value.trim_start().starts_with('{') {
+        value.clone()
+    } else {
+        serde_json::json!({ "state": value.trim() }).to_string()
+    };
+    match serde_json::from_str(&json) {
+        Ok(status) => {
+            log::warn!("BRIAN_MOCK_BACKEND={}: using a mock backend", value);
+            apply(app, status);
+            true
+        }
+        Err(e) => {
+            log::error!("Ignoring BRIAN_MOCK_BACKEND={}: {}", value, e);
+            false
+        }
+    }
+}
+
+/// Turn the mock backend on (if it isn't already) and move it to `status`,
+/// emitting the same events a real transition would.
+pub(crate) fn set(app: &AppHandle, status: MockStatus) -> Result<(), String> {
+    if !cfg!(debug_assertions) {
+        return Err("the mock backend is only available in debug builds".to_string());
+    }
+    apply(app, status);
+    Ok(())
+}
+
+fn apply(app: &AppHandle, status: MockStatus) {
+    if !app.state::<MockBackend>().0.swap(true, Ordering::SeqCst) {
+        log::warn!("Mock backend on: not spawning or health checking the real one");
+    }
+    let fallback_port = app.state::<BrianConfig>().fallback_port;
+    let status = match status {
+        MockStatus::Starting { phase } => BackendStatus::Starting {
+            phase: phase.unwrap_or(StartupPhase::Launching),
+        },
+        MockStatus::Healthy { port } => BackendStatus::Healthy {
+            port: port.unwrap_or(fallback_port),
+        },
+        MockStatus::Unhealthy {
+            kind,
+            reason,
+            detail,
+        } => BackendStatus::unhealthy(
+            kind.unwrap_or(ErrorKind::HealthTimeout),
+            reason.unwrap_or_else(|| "mock backend error".to_string()),
+            detail,
+        ),
+        MockStatus::PortConflict { port } => {
+            let port = port.unwrap_or(fallback_port);
+            let _ = app.emit("backend-port-conflict", port);
+            BackendStatus::unhealthy(
+                ErrorKind::PortConflict,
+                format!("port {} is already in use", port),
+                None,
+            )
+        }
+        MockStatus::Stopped => BackendStatus::Stopped,
+    };
+    set_status(app, status);
+}
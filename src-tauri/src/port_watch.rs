@@ -0,0 +1,84 @@
+//! Keeps the frontend on the right port for the whole session. The health
+//! check only re-reads the port file while starting up; this watcher notices
+//! later rewrites of it or of `connection.json` (e.g. the backend rebinding)
+//! and emits `backend-port-changed`.
+
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::status::{set_status, BackendState, BackendStatus};
+
+/// Quiet period after the last change before the file is re-read, so a
+/// truncate-then-write counts as one change.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watch the port file for the app's lifetime on a background thread.
+/// `connection.json` is only noticed when it lives next to the port file,
+/// which it does unless `BRIAN_PORT_FILE` moves the port file elsewhere.
+pub(crate) fn spawn(app: AppHandle) {
+    let path = crate::port_file();
+    let connection = crate::connection::connection_file();
+    // Watch the directory rather than the file, which the backend may delete
+    // and recreate.
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        log::warn!("Not watching {}: {}", path.display(), e);
+        return;
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| event.paths.iter().any(|p| p == path || *p == connection)) {
+            let _ = tx.send(());
+        }
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            log::warn!("Failed to create port file watcher: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+        log::warn!("Failed to watch {}: {}", dir.display(), e);
+        return;
+    }
+
+    std::thread::spawn(move || {
+        // Owned by the thread so watching lasts as long as it does.
+        let _watcher = watcher;
+        let mut last = read_port(path);
+        while rx.recv().is_ok() {
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+            let port = read_port(path);
+            if port == last {
+                continue;
+            }
+            last = port;
+            if let Some(port) = port {
+                port_changed(&app, port);
+            }
+        }
+    });
+}
+
+/// The port in `connection.json`, else the one in the port file at `path`.
+fn read_port(path: &Path) -> Option<u16> {
+    if let Some(port) = crate::connection::read_file().and_then(|file| file.port) {
+        return Some(port);
+    }
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn port_changed(app: &AppHandle, port: u16) {
+    log::info!("Port file changed, backend now on port {}", port);
+    let _ = app.emit("backend-port-changed", port);
+    if let BackendStatus::Healthy { .. } = app.state::<BackendState>().get() {
+        set_status(app, BackendStatus::Healthy { port });
+    }
+}
@@ -0,0 +1,114 @@
+//! A cleanup tool for sidecars orphaned by crashes: list every running
+//! process that looks like our sidecar, and kill one on request.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Manager};
+
+use crate::sidecar::{sidecar_name, Sidecars};
+
+#[derive(Debug, Serialize)]
+pub(crate) struct BackendProcess {
+    pid: u32,
+    name: String,
+    /// Whether this is the sidecar the app is currently running.
+    current: bool,
+    /// The profile whose `backend.pid` names this process, if any (`"default"`
+    /// for the plain ~/.brian layout).
+    profile: Option<String>,
+    /// That profile's port file, if the process was found in a PID file.
+    port: Option<u16>,
+}
+
+/// Every running process whose name starts with the sidecar's, matched up
+/// with the PID files of all profiles.
+pub(crate) fn list(app: &AppHandle) -> Vec<BackendProcess> {
+    let known = pid_files();
+    let current = app.state::<Sidecars>().pid();
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+    let mut processes: Vec<BackendProcess> = system
+        .processes()
+        .values()
+        .filter(|process| is_sidecar(process))
+        .map(|process| {
+            let pid = process.pid().as_u32();
+            let (profile, port) = known.get(&pid).cloned().unzip();
+            BackendProcess {
+                pid,
+                name: process.name().to_string_lossy().into_owned(),
+                current: current == Some(pid),
+                profile,
+                port: port.flatten(),
+            }
+        })
+        .collect();
+    processes.sort_by_key(|p| p.pid);
+    processes
+}
+
+/// Kill process `pid`, provided it is one of ours and not the sidecar in use
+/// (which `stop_backend` or `restart_backend` should handle).
+pub(crate) fn kill(app: &AppHandle, pid: u32) -> Result<(), String> {
+    if app.state::<Sidecars>().pid() == Some(pid) {
+        return Err(format!(
+            "pid {} is the running {}; stop it with stop_backend instead",
+            pid,
+            sidecar_name()
+        ));
+    }
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+    let process = system
+        .process(pid)
+        .ok_or_else(|| format!("no process with pid {}", pid))?;
+    if !is_sidecar(process) {
+        return Err(format!("pid {} is not a {} process", pid, sidecar_name()));
+    }
+    log::warn!("Killing stray {} (pid {}) on request", sidecar_name(), pid);
+    if process.kill() {
+        Ok(())
+    } else {
+        Err(format!("failed to kill pid {}", pid))
+    }
+}
+
+fn is_sidecar(process: &sysinfo::Process) -> bool {
+    process.name().to_string_lossy().starts_with(sidecar_name())
+}
+
+/// PID → (profile, port) for every profile's `backend.pid`.
+fn pid_files() -> HashMap<u32, (String, Option<u16>)> {
+    let brian_dir = crate::brian_dir();
+    let profiles = std::fs::read_dir(&brian_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| (entry.file_name().to_string_lossy().into_owned(), entry.path()));
+
+    std::iter::once(("default".to_string(), brian_dir.clone()))
+        .chain(profiles)
+        .filter_map(|(profile, dir)| {
+            let pid = read_number::<u32>(&dir.join("backend.pid"))?;
+            Some((pid, (profile, read_number(&dir.join("port")))))
+        })
+        .collect()
+}
+
+fn read_number<T: std::str::FromStr>(path: &Path) -> Option<T> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
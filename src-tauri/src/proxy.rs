@@ -0,0 +1,233 @@
+//! Backend requests made on the webview's behalf, so it never deals with
+//! ports, schemes, sockets or CORS itself.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use http::uri::PathAndQuery;
+use reqwest::Method;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+/// Responses larger than this are refused rather than buffered.
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Overall deadline for a proxied request, body included.
+pub(crate) const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Managed HTTP client shared by all proxied requests, built once from the
+/// config's TLS settings.
+pub(crate) struct BackendClient(pub reqwest::Client);
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ProxyResponse {
+    status: u16,
+    headers: HashMap<String, String>,
+    /// The body as text; invalid UTF-8 is replaced.
+    body: String,
+}
+
+/// Send `method path` to the backend with `headers` and `body`, returning
+/// its response, over the Unix socket if it published one and TCP
+/// otherwise. `path` must be a valid path and query starting with `/`, and
+/// `method` a valid method; both go into the request line as is.
+pub(crate) async fn send(
+    app: &AppHandle,
+    method: &str,
+    path: &str,
+    headers: HashMap<String, String>,
+    body: Option<String>,
+) -> Result<ProxyResponse, String> {
+    if !path.starts_with('/') {
+        return Err(format!("path {:?} must start with '/'", path));
+    }
+    // Rejects whitespace and control characters, so nothing can be smuggled
+    // into the socket route's request line.
+    let path = PathAndQuery::from_str(path).map_err(|_| format!("invalid path {:?}", path))?;
+    let path = path.as_str();
+    let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+        .map_err(|_| format!("invalid method {:?}", method))?;
+
+    let connection = crate::read_backend_connection(app)?;
+    if let Some(socket) = &connection.socket {
+        let token = connection.token.as_deref();
+        return send_socket(socket, &method, path, token, &headers, body).await;
+    }
+    let url = format!("{}{}", connection.base_url(), path);
+    let mut request = app
+        .state::<BackendClient>()
+        .0
+        .request(method.clone(), &url)
+        .timeout(TIMEOUT);
+    // The caller's own credentials win over the backend's token.
+    if !headers.keys().any(|name| name.eq_ignore_ascii_case("authorization")) {
+        request = connection.authorize(request);
+    }
+    for (name, value) in &headers {
+        request = request.header(name, value);
+    }
+    if let Some(body) = body {
+        request = request.body(body);
+    }
+
+    let mut resp = request
+        .send()
+        .await
+        .map_err(|e| format!("{} {} failed: {}", method, url, e))?;
+    if resp.content_length().is_some_and(|len| len > MAX_BODY_BYTES as u64) {
+        return Err(format!("{} {} response too large", method, url));
+    }
+
+    let status = resp.status().as_u16();
+    let headers = resp
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    // Read chunk by chunk so an unexpectedly huge body is cut off, not buffered.
+    let mut bytes = Vec::new();
+    while let Some(chunk) = resp
+        .chunk()
+        .await
+        .map_err(|e| format!("failed to read {} {} response: {}", method, url, e))?
+    {
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(format!("{} {} response too large", method, url));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(ProxyResponse {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&bytes).into_owned(),
+    })
+}
+
+/// [`send`] over the backend's Unix socket, which `reqwest` can't dial: one
+/// bare HTTP/1.1 exchange per connection, read until the backend closes it.
+#[cfg(unix)]
+async fn send_socket(
+    socket: &Path,
+    method: &Method,
+    path: &str,
+    token: Option<&str>,
+    headers: &HashMap<String, String>,
+    body: Option<String>,
+) -> Result<ProxyResponse, String> {
+    use reqwest::header::{HeaderName, HeaderValue};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let what = format!("{} {} over socket {}", method, path, socket.display());
+    let body = body.unwrap_or_default();
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        body.len()
+    );
+    let mut authorized = false;
+    for (name, value) in headers {
+        // Validated like `reqwest` would, which also rules out smuggling in
+        // extra header lines.
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("invalid header name {:?}", name))?;
+        HeaderValue::from_str(value).map_err(|_| format!("invalid value for header {}", name))?;
+        if ["host", "connection", "content-length"].contains(&name.as_str()) {
+            continue;
+        }
+        authorized |= name == reqwest::header::AUTHORIZATION;
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    // The caller's own credentials win over the backend's token.
+    if let (Some(token), false) = (token, authorized) {
+        request.push_str(&format!("Authorization: Bearer {}\r\n", token));
+    }
+    request.push_str("\r\n");
+
+    let exchange = async {
+        let mut stream = tokio::net::UnixStream::connect(socket).await?;
+        stream.write_all(request.as_bytes()).await?;
+        stream.write_all(body.as_bytes()).await?;
+        // One byte past the limit is enough to know the body is too large.
+        let mut raw = Vec::new();
+        stream.take((MAX_HEAD_BYTES + MAX_BODY_BYTES) as u64 + 1).read_to_end(&mut raw).await?;
+        Ok::<_, std::io::Error>(raw)
+    };
+    let raw = exchange.await.map_err(|e| format!("{} failed: {}", what, e))?;
+    if raw.len() > MAX_HEAD_BYTES + MAX_BODY_BYTES {
+        return Err(format!("{} response too large", what));
+    }
+    let response = parse_response(&raw)
+        .ok_or_else(|| format!("{} returned a malformed response", what))?;
+    if response.body.len() > MAX_BODY_BYTES {
+        return Err(format!("{} response too large", what));
+    }
+    Ok(response)
+}
+
+#[cfg(not(unix))]
+async fn send_socket(
+    _socket: &Path,
+    _method: &Method,
+    _path: &str,
+    _token: Option<&str>,
+    _headers: &HashMap<String, String>,
+    _body: Option<String>,
+) -> Result<ProxyResponse, String> {
+    Err("Unix sockets are not supported on this platform".to_string())
+}
+
+/// Room allowed for a socket response's status line and headers.
+#[cfg(unix)]
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// Split a complete HTTP/1.1 response into status, headers (names
+/// lowercased, as `reqwest` reports them) and body, honouring
+/// `Content-Length` and chunked transfer encoding.
+#[cfg(unix)]
+fn parse_response(raw: &[u8]) -> Option<ProxyResponse> {
+    let split = raw.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&raw[..split]).ok()?;
+    let mut lines = head.split("\r\n");
+    let status = lines.next()?.split_whitespace().nth(1)?.parse::<u16>().ok()?;
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect();
+
+    let mut body = &raw[split + 4..];
+    let decoded;
+    if headers.get("transfer-encoding").is_some_and(|te| te.eq_ignore_ascii_case("chunked")) {
+        decoded = decode_chunked(body)?;
+        body = &decoded;
+    } else if let Some(len) = headers.get("content-length") {
+        body = body.get(..len.parse::<usize>().ok()?)?;
+    }
+    Some(ProxyResponse {
+        status,
+        headers,
+        body: String::from_utf8_lossy(body).into_owned(),
+    })
+}
+
+/// The payload of a chunked body, or `None` if it's cut short.
+#[cfg(unix)]
+fn decode_chunked(mut data: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&data[..line_end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Some(body);
+        }
+        body.extend_from_slice(data.get(..size)?);
+        data = data.get(size..)?.strip_prefix(b"\r\n")?;
+    }
+}
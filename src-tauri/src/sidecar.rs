@@ -0,0 +1,1382 @@
+//! Lifecycle of the backend sidecar process: spawning, streaming its
+//! output, stopping it, and restarting it after crashes. Extra sidecars from
+//! `sidecars` in the config run alongside it and are stopped before it.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError, TryLockError};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tauri::async_runtime::Receiver;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::ShellExt;
+use tauri_plugin_shell::process::{Command, CommandChild, CommandEvent, TerminatedPayload};
+
+use crate::config::{BrianConfig, ExtraSidecar};
+use crate::error::CommandError;
+use crate::proxy::BackendClient;
+use crate::health;
+use crate::logs::{record_line, LogBatch};
+use crate::redact::Redactor;
+use crate::status::{set_status, set_stopped, BackendStatus, ErrorKind};
+use crate::capabilities::CapabilitiesCache;
+use crate::storage::StorageState;
+use crate::version::VersionCache;
+
+/// Sidecar name used when neither the config nor the build overrides it.
+const DEFAULT_SIDECAR_NAME: &str = "brian-backend";
+
+static SIDECAR_NAME: OnceLock<String> = OnceLock::new();
+
+/// Resolve the sidecar binary name once, at startup: `sidecar_name` from the
+/// config file, else `BRIAN_SIDECAR_NAME` at compile time, else
+/// `brian-backend`. A renamed backend must also be listed under
+/// `bundle.externalBin` in `tauri.conf.json`.
+pub(crate) fn init_sidecar_name(config: &BrianConfig) {
+    let name = config
+        .sidecar_name
+        .clone()
+        .or_else(|| option_env!("BRIAN_SIDECAR_NAME").map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_SIDECAR_NAME.to_string());
+    if SIDECAR_NAME.set(name).is_err() {
+        log::warn!("Sidecar name already resolved, ignoring reinitialization");
+    }
+}
+
+/// The sidecar binary name, as resolved by [`init_sidecar_name`].
+pub(crate) fn sidecar_name() -> &'static str {
+    SIDECAR_NAME.get().map_or(DEFAULT_SIDECAR_NAME, String::as_str)
+}
+
+/// A running sidecar plus a flag its log-streaming task sets once the
+/// process has exited, so shutdown can wait for it.
+pub(crate) struct NamedChild {
+    /// Binary name; the backend's is [`sidecar_name`].
+    pub name: String,
+    pub child: SidecarChild,
+    pub exited: Arc<AtomicBool>,
+    /// Which spawn this is; tasks tied to an older one drop their events.
+    pub generation: u64,
+    /// How long it gets to exit after being asked before it is killed.
+    pub shutdown_timeout: Duration,
+}
+
+impl NamedChild {
+    fn is_backend(&self) -> bool {
+        self.name == sidecar_name()
+    }
+}
+
+/// A sidecar's process handle. Clones share it, so a stdin write can run
+/// without holding the [`Sidecars`] lock.
+#[derive(Clone)]
+pub(crate) struct SidecarChild {
+    pid: u32,
+    /// Emptied by [`kill`](Self::kill).
+    child: Arc<Mutex<Option<CommandChild>>>,
+}
+
+impl SidecarChild {
+    fn new(child: CommandChild) -> Self {
+        Self { pid: child.pid(), child: Arc::new(Mutex::new(Some(child))) }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Write all of `data` to the child's stdin; blocks while the pipe is
+    /// full.
+    pub fn write(&self, data: &[u8]) -> Result<(), String> {
+        let mut child = self.child.lock().unwrap_or_else(PoisonError::into_inner);
+        let child = child.as_mut().ok_or_else(|| "process was killed".to_string())?;
+        child.write(data).map_err(|e| e.to_string())
+    }
+
+    /// Kill the process. A write stuck on a full pipe holds the handle, so
+    /// then it's killed by PID instead, which also unblocks the write.
+    fn kill(self) -> Result<(), String> {
+        let child = match self.child.try_lock() {
+            Ok(mut child) => child.take(),
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().take(),
+            Err(TryLockError::WouldBlock) => {
+                let mut system = System::new();
+                let pid = Pid::from_u32(self.pid);
+                system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+                return match system.process(pid) {
+                    Some(process) if process.kill() => Ok(()),
+                    _ => Err(format!("failed to kill pid {}", self.pid)),
+                };
+            }
+        };
+        match child {
+            Some(child) => child.kill().map_err(|e| e.to_string()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Source of [`NamedChild::generation`]s.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// Pseudo-generation for health checks against a backend we didn't spawn
+/// (`--no-sidecar`). Current for as long as no sidecar has been spawned.
+pub(crate) const ATTACHED: u64 = 0;
+
+/// Holds the running sidecars so we can stop them on app exit, in start
+/// order: the backend first, then the extras that depend on it. A respawned
+/// backend takes its place back at the front.
+pub(crate) struct Sidecars(pub Mutex<Vec<NamedChild>>);
+
+impl Sidecars {
+    /// Lock the state, recovering from a panic in another holder: the list
+    /// is always valid on its own, and refusing the lock here would leak the
+    /// sidecars on exit.
+    fn lock(&self) -> MutexGuard<'_, Vec<NamedChild>> {
+        self.0.lock().unwrap_or_else(|poisoned| {
+            log::warn!("Sidecar state lock was poisoned by a panic, recovering it");
+            self.0.clear_poison();
+            poisoned.into_inner()
+        })
+    }
+
+    /// Take the backend out, leaving any extras running.
+    fn take(&self) -> Option<NamedChild> {
+        let mut guard = self.lock();
+        let index = guard.iter().position(NamedChild::is_backend)?;
+        Some(guard.remove(index))
+    }
+
+    /// Take every sidecar out, in start order.
+    fn take_all(&self) -> Vec<NamedChild> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Drop the sidecar from spawn `generation`; returns whether it was held.
+    fn remove(&self, generation: u64) -> bool {
+        let mut guard = self.lock();
+        let before = guard.len();
+        guard.retain(|sidecar| sidecar.generation != generation);
+        guard.len() != before
+    }
+
+    /// Whether the backend is running.
+    pub fn is_running(&self) -> bool {
+        self.lock().iter().any(NamedChild::is_backend)
+    }
+
+    /// The running backend's handle, for writing to its stdin outside the
+    /// lock. The handle goes with the process, so a stopped or respawning
+    /// backend is an error.
+    pub fn backend_child(&self) -> Result<SidecarChild, String> {
+        self.lock()
+            .iter()
+            .find(|sidecar| sidecar.is_backend() && !sidecar.exited.load(Ordering::SeqCst))
+            .map(|backend| backend.child.clone())
+            .ok_or_else(|| format!("{} is not running", sidecar_name()))
+    }
+
+    /// PID of the running backend, if any.
+    pub fn pid(&self) -> Option<u32> {
+        let guard = self.lock();
+        guard.iter().find(|sidecar| sidecar.is_backend()).map(|sidecar| sidecar.child.pid())
+    }
+}
+
+/// Set once the app starts tearing down, so sidecar exits during shutdown
+/// aren't mistaken for crashes and auto-restarted.
+pub(crate) struct ShuttingDown(pub AtomicBool);
+
+/// The port the sidecar is asked to bind via `BRIAN_PORT`, set by
+/// `rebind_backend`; `0` asks for an ephemeral port. `None` leaves the choice
+/// to the backend. Kept across respawns so auto-restart stays on the new port.
+pub(crate) struct RequestedPort(pub Mutex<Option<u16>>);
+
+/// The port the current sidecar announced on stdout (see `port_markers`),
+/// for when it can't write the port file.
+pub(crate) struct StdoutPort(pub Mutex<Option<u16>>);
+
+impl StdoutPort {
+    pub fn get(&self) -> Option<u16> {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set(&self, port: Option<u16>) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = port;
+    }
+}
+
+/// The secret the backend must see as a bearer `Authorization` header on
+/// every request, so other local processes can't use it. Passed to the
+/// sidecar as `BRIAN_AUTH_TOKEN`, regenerated on every spawn, and only ever
+/// kept in memory. `None` for a backend we didn't spawn.
+#[derive(Default)]
+pub(crate) struct AuthToken(Mutex<Option<String>>);
+
+impl AuthToken {
+    pub fn get(&self) -> Option<String> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Replace the token with a fresh random one and return it.
+    fn rotate(&self) -> String {
+        let bytes: [u8; 32] = rand::random();
+        let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(token.clone());
+        token
+    }
+}
+
+/// Bookkeeping for restarting the sidecar after it crashes.
+/// Opt-in via `auto_restart` in the config file.
+pub(crate) struct AutoRestart {
+    /// Seeded from `auto_restart` in the config; toggled at runtime by
+    /// `set_auto_restart`.
+    enabled: AtomicBool,
+    attempts: AtomicU32,
+    /// When the current sidecar last passed its health check.
+    healthy_since: Mutex<Option<Instant>>,
+    /// Recent crashes and how each process exited, for the crash-loop breaker.
+    crashes: Mutex<VecDeque<(Instant, String)>>,
+    /// Set once more than `crash_loop_max` crashes land within
+    /// `crash_loop_window`; only a manual restart clears it.
+    tripped: AtomicBool,
+    crash_loop_max: usize,
+    crash_loop_window: Duration,
+    delay: Duration,
+    confirm: bool,
+}
+
+impl AutoRestart {
+    /// Give up after this many consecutive crashes.
+    const MAX_ATTEMPTS: u32 = 3;
+    /// A backend that stayed healthy this long earns a fresh restart budget.
+    const RESET_AFTER: Duration = Duration::from_secs(60);
+
+    pub fn new(config: &BrianConfig) -> Self {
+        Self {
+            enabled: AtomicBool::new(config.auto_restart),
+            attempts: AtomicU32::new(0),
+            healthy_since: Mutex::new(None),
+            crashes: Mutex::new(VecDeque::new()),
+            tripped: AtomicBool::new(false),
+            crash_loop_max: config.crash_loop_max_restarts,
+            crash_loop_window: Duration::from_secs(config.crash_loop_window_secs),
+            delay: Duration::from_secs(config.auto_restart_delay_secs),
+            confirm: config.auto_restart_confirm,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn mark_healthy(&self) {
+        *self.healthy_since.lock().unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+    }
+
+    /// Forget past crashes and close the breaker, e.g. after a manual restart.
+    pub fn reset(&self) {
+        self.attempts.store(0, Ordering::SeqCst);
+        self.crashes.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        self.tripped.store(false, Ordering::SeqCst);
+    }
+
+    fn tripped(&self) -> bool {
+        self.tripped.load(Ordering::SeqCst)
+    }
+
+    /// Note a crash that exited with `exit`. Returns the exit statuses within
+    /// the window if this crash trips the breaker.
+    fn record_crash(&self, exit: String) -> Option<Vec<String>> {
+        let mut crashes = self.crashes.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        crashes.push_back((now, exit));
+        while crashes
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > self.crash_loop_window)
+        {
+            crashes.pop_front();
+        }
+        if crashes.len() <= self.crash_loop_max {
+            return None;
+        }
+        self.tripped.store(true, Ordering::SeqCst);
+        Some(crashes.iter().map(|(_, exit)| exit.clone()).collect())
+    }
+
+    /// Claim the next restart attempt number, or `None` once the budget is spent.
+    fn next_attempt(&self) -> Option<u32> {
+        let healthy_since =
+            self.healthy_since.lock().unwrap_or_else(PoisonError::into_inner).take();
+        if healthy_since.is_some_and(|t| t.elapsed() >= Self::RESET_AFTER) {
+            self.attempts.store(0, Ordering::SeqCst);
+        }
+        let attempt = self.attempts.fetch_add(1, Ordering::SeqCst) + 1;
+        (attempt <= Self::MAX_ATTEMPTS).then_some(attempt)
+    }
+}
+
+/// Substrings that mark an env var as secret; its value is never logged.
+const SENSITIVE_ENV_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD", "CREDENTIAL"];
+
+pub(crate) fn is_sensitive_env(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    SENSITIVE_ENV_MARKERS.iter().any(|m| key.contains(m))
+}
+
+/// Environment applied to every sidecar spawn, on top of the app's own
+/// (inherited) environment. By default the shell injects:
+///
+/// - `BRIAN_DESKTOP=1` — tells the backend it runs under the desktop app
+/// - `BRIAN_APP_VERSION` — the desktop shell's version
+/// - `BRIAN_PARENT_PID` — this process's PID, so the backend can exit if the
+///   shell dies without running its cleanup (Brian's own polls it)
+/// - `BRIAN_PORT` — only after `rebind_backend`, the port to listen on
+/// - `BRIAN_PROFILE` — only for a non-default profile, its name
+///
+/// Entries from `backend_env` in the config file are applied next, then
+/// `hook_env` exported by `pre_spawn_command`; either may override the
+/// defaults. `BRIAN_AUTH_TOKEN` (see [`AuthToken`]) comes last, so nothing
+/// can replace it with one the app doesn't send.
+fn sidecar_env(
+    config: &BrianConfig,
+    port: Option<u16>,
+    hook_env: Vec<(String, String)>,
+    token: Option<String>,
+) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("BRIAN_DESKTOP".to_string(), "1".to_string()),
+        ("BRIAN_APP_VERSION".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+        ("BRIAN_PARENT_PID".to_string(), std::process::id().to_string()),
+    ];
+    if let Some(port) = port {
+        env.push(("BRIAN_PORT".to_string(), port.to_string()));
+    }
+    if let Some(profile) = crate::profile() {
+        env.push(("BRIAN_PROFILE".to_string(), profile.to_string()));
+    }
+    let overrides = config.backend_env.iter().map(|(k, v)| (k.clone(), v.clone()));
+    let token = token.map(|token| ("BRIAN_AUTH_TOKEN".to_string(), token));
+    for (key, value) in overrides.chain(hook_env).chain(token) {
+        env.retain(|(k, _)| *k != key);
+        env.push((key, value));
+    }
+
+    log::info!("Sidecar environment: {}", redact_env(&env).join(", "));
+    env
+}
+
+/// `KEY=value` strings for `env`, with sensitive values masked as `***`.
+pub(crate) fn redact_env<'a>(env: impl IntoIterator<Item = &'a (String, String)>) -> Vec<String> {
+    env.into_iter()
+        .map(|(k, v)| {
+            if is_sensitive_env(k) {
+                format!("{}=***", k)
+            } else {
+                format!("{}={}", k, v)
+            }
+        })
+        .collect()
+}
+
+/// Arguments from `backend_args`. They go straight into argv, so shell syntax
+/// is never interpreted; arguments that look like it are logged in case
+/// someone expected it to be.
+fn sidecar_args(config: &BrianConfig) -> &[String] {
+    const SHELL_CHARS: &[char] = &['|', '&', ';', '<', '>', '$', '`', '*', '?', '(', ')'];
+    for arg in &config.backend_args {
+        if arg.contains(SHELL_CHARS) {
+            log::warn!(
+                "Sidecar argument {:?} contains shell syntax, which is passed through literally",
+                arg
+            );
+        }
+    }
+    if !config.backend_args.is_empty() {
+        log::info!("Sidecar arguments: {:?}", config.backend_args);
+    }
+    &config.backend_args
+}
+
+/// Spawn the backend sidecar, store its handle in `Sidecars`,
+/// and start the log-streaming and health-check tasks for it. Blocks while
+/// `pre_spawn_command` runs; async code goes through [`spawn_backend_async`].
+pub(crate) fn spawn_backend(app: &AppHandle) -> Result<(), SpawnError> {
+    if crate::mock::active(app) {
+        log::info!("Mock backend on, not spawning {}", sidecar_name());
+        return Ok(());
+    }
+    log::info!("Spawning {} sidecar…", sidecar_name());
+
+    let config = app.state::<BrianConfig>();
+    let hook_env = match &config.pre_spawn_command {
+        Some(command) => run_pre_spawn(app, command).map_err(|message| SpawnError {
+            kind: ErrorKind::PreSpawnFailed,
+            message,
+        })?,
+        None => Vec::new(),
+    };
+
+    let sidecar_cmd = backend_command(app, &config)?;
+
+    let cwd = sidecar_cwd(&config)?;
+    let port = *app.state::<RequestedPort>().0.lock().unwrap_or_else(PoisonError::into_inner);
+    // No console window flashes up on Windows: the shell plugin creates every
+    // command with `CREATE_NO_WINDOW`, and its stdout / stderr are pipes
+    // feeding `rx`, which don't need a console to be captured.
+    let (rx, child) = sidecar_cmd
+        .current_dir(cwd)
+        .args(sidecar_args(&config))
+        .envs(sidecar_env(&config, port, hook_env, Some(app.state::<AuthToken>().rotate())))
+        .spawn()
+        .map_err(|e| format!("failed to spawn {} sidecar: {}", sidecar_name(), e))?;
+    let pid = child.pid();
+    let exited = Arc::new(AtomicBool::new(false));
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::SeqCst);
+    write_pid_file(pid);
+    #[cfg(windows)]
+    kill_with_parent(pid);
+
+    // Store the child handle in managed state for cleanup.
+    app.state::<Sidecars>().lock().insert(
+        0,
+        NamedChild {
+            name: sidecar_name().to_string(),
+            child: SidecarChild::new(child),
+            exited: exited.clone(),
+            generation,
+            shutdown_timeout: Duration::from_secs(config.shutdown_timeout_secs),
+        },
+    );
+
+    log::info!("{} sidecar spawned (pid {}), streaming output…", sidecar_name(), pid);
+    app.state::<VersionCache>().clear();
+    app.state::<CapabilitiesCache>().clear();
+    app.state::<StorageState>().clear();
+    app.state::<StdoutPort>().set(None);
+    set_status(app, BackendStatus::LAUNCHING);
+
+    spawn_log_stream(app.clone(), rx, pid, generation, exited);
+    health::spawn_health_check(app.clone(), generation);
+    Ok(())
+}
+
+/// The sidecar's working directory, `backend_cwd` or the state dir, created
+/// if missing so the sidecar never depends on where the app was launched.
+fn sidecar_cwd(config: &BrianConfig) -> Result<PathBuf, String> {
+    let cwd = match &config.backend_cwd {
+        Some(dir) => crate::state_dir().join(dir),
+        None => crate::state_dir(),
+    };
+    std::fs::create_dir_all(&cwd)
+        .map_err(|e| format!("failed to create working directory {}: {}", cwd.display(), e))?;
+    log::info!("Sidecar working directory: {}", cwd.display());
+    Ok(cwd)
+}
+
+/// The command that starts the backend: the interpreter in script mode,
+/// otherwise the bundled sidecar, under `backend_launcher` if there is one.
+fn backend_command(app: &AppHandle, config: &BrianConfig) -> Result<Command, String> {
+    if let Some(interpreter) = script_interpreter(config) {
+        log::warn!(
+            "Script mode: running the backend as {} {:?} instead of the bundled {} sidecar",
+            interpreter,
+            config.backend_script_args,
+            sidecar_name()
+        );
+        return Ok(app.shell().command(interpreter).args(&config.backend_script_args));
+    }
+    log::info!("Running the bundled {} sidecar", sidecar_name());
+    let sidecar_cmd = app
+        .shell()
+        .sidecar(sidecar_name())
+        .map_err(|e| format!("failed to create {} sidecar command: {}", sidecar_name(), e))?;
+    Ok(match launcher(config) {
+        Some(launcher) => under_launcher(app, sidecar_cmd, launcher),
+        None => sidecar_cmd,
+    })
+}
+
+/// `backend_interpreter`, if it is set and installed. A missing one is
+/// logged, and the bundled sidecar is used instead.
+fn script_interpreter(config: &BrianConfig) -> Option<&str> {
+    let interpreter = config.backend_interpreter.as_deref()?;
+    if !program_exists(interpreter) {
+        log::error!(
+            "backend_interpreter {:?} not found, using the bundled {} sidecar",
+            interpreter,
+            sidecar_name()
+        );
+        return None;
+    }
+    Some(interpreter)
+}
+
+/// `backend_launcher`, if it is set, allowed, and its program installed.
+/// Anything else is logged, and the sidecar is launched directly.
+fn launcher(config: &BrianConfig) -> Option<&[String]> {
+    let (program, _) = config.backend_launcher.split_first()?;
+    if !cfg!(debug_assertions) && !config.allow_backend_launcher {
+        log::error!("Ignoring backend_launcher: release builds need allow_backend_launcher");
+        return None;
+    }
+    if !program_exists(program) {
+        log::error!(
+            "backend_launcher {:?} not found, launching {} directly",
+            program,
+            sidecar_name()
+        );
+        return None;
+    }
+    Some(&config.backend_launcher)
+}
+
+/// `launcher` with the resolved sidecar binary appended, for the sidecar's
+/// own arguments to follow.
+fn under_launcher(app: &AppHandle, sidecar: Command, launcher: &[String]) -> Command {
+    let binary = std::process::Command::from(sidecar).get_program().to_owned();
+    log::info!("Launching {} under {:?}", sidecar_name(), launcher);
+    app.shell().command(&launcher[0]).args(&launcher[1..]).arg(binary)
+}
+
+/// Whether `program` is a file, or a bare name found on `PATH`.
+fn program_exists(program: &str) -> bool {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.is_file();
+    }
+    let extensions: &[&str] = if cfg!(windows) { &["", ".exe", ".cmd", ".bat"] } else { &[""] };
+    let Some(dirs) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&dirs).any(|dir| {
+        extensions
+            .iter()
+            .any(|ext| dir.join(format!("{}{}", program, ext)).is_file())
+    })
+}
+
+/// [`spawn_backend`] on the blocking pool, so a slow `pre_spawn_command`
+/// doesn't stall a runtime worker.
+pub(crate) async fn spawn_backend_async(app: &AppHandle) -> Result<(), SpawnError> {
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || spawn_backend(&handle))
+        .await
+        .map_err(|e| SpawnError::from(format!("failed to spawn backend: {}", e)))?
+}
+
+/// Why [`spawn_backend`] failed.
+#[derive(Debug)]
+pub(crate) struct SpawnError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl SpawnError {
+    /// The status to report for this failure.
+    pub fn status(&self) -> BackendStatus {
+        BackendStatus::unhealthy(self.kind, self.message.clone(), None)
+    }
+}
+
+impl From<String> for SpawnError {
+    fn from(message: String) -> Self {
+        Self {
+            kind: ErrorKind::SpawnFailed,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Run `command` through the platform shell before a spawn and collect the
+/// `KEY=VALUE` lines it prints (`export KEY=VALUE` works too) for the
+/// sidecar's environment. Anything else on stdout is ignored. Fails unless
+/// the command exits 0.
+fn run_pre_spawn(app: &AppHandle, command: &str) -> Result<Vec<(String, String)>, String> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    log::info!("Running pre_spawn_command: {}", command);
+    // Converted to a std command so it can run to completion synchronously.
+    let output = std::process::Command::from(app.shell().command(shell).args([flag, command]))
+        .output()
+        .map_err(|e| format!("failed to run pre_spawn_command: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("pre_spawn_command failed ({}): {}", output.status, stderr.trim()));
+    }
+
+    let env: Vec<(String, String)> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (key, value) = line.strip_prefix("export ").unwrap_or(line).split_once('=')?;
+            let valid = !key.is_empty()
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            valid.then(|| (key.to_string(), value.trim_matches('"').to_string()))
+        })
+        .collect();
+    log::info!(
+        "pre_spawn_command succeeded, exporting: {}",
+        redact_env(&env).join(", ")
+    );
+    Ok(env)
+}
+
+/// Spawn the sidecar from app startup, retrying a couple of times so a
+/// transient failure doesn't cost the session. A sidecar that can't be
+/// started at all (missing binary, bad permissions) ends up `Unhealthy` with
+/// the reason, leaving the window open to show it rather than crashing.
+/// Once it is spawned, the extra `sidecars` from the config follow.
+pub(crate) fn spawn_backend_with_retries(app: AppHandle) {
+    const ATTEMPTS: u32 = 3;
+    const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+    tauri::async_runtime::spawn(async move {
+        for attempt in 1..=ATTEMPTS {
+            let Err(e) = spawn_backend_async(&app).await else {
+                spawn_extra_sidecars(&app);
+                return;
+            };
+            if attempt == ATTEMPTS {
+                log::error!(
+                    "Giving up on starting {} after {} attempts: {}",
+                    sidecar_name(),
+                    attempt,
+                    e
+                );
+                set_status(&app, e.status());
+                return;
+            }
+            log::warn!("Spawn attempt {}/{} failed: {}, retrying…", attempt, ATTEMPTS, e);
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    });
+}
+
+/// Spawn each of the config's extra `sidecars` not already running. They
+/// get the backend's environment and their own `args`; their output goes to
+/// the app log, and they aren't restarted when they exit.
+fn spawn_extra_sidecars(app: &AppHandle) {
+    if crate::mock::active(app) {
+        return;
+    }
+    let config = app.state::<BrianConfig>();
+    for extra in &config.sidecars {
+        if extra.name == sidecar_name() {
+            log::error!("Sidecar {} is the backend itself, skipping it", extra.name);
+            continue;
+        }
+        if app.state::<Sidecars>().lock().iter().any(|s| s.name == extra.name) {
+            continue;
+        }
+        if let Err(e) = spawn_extra(app, &config, extra) {
+            log::error!("{}", e);
+        }
+    }
+}
+
+fn spawn_extra(app: &AppHandle, config: &BrianConfig, extra: &ExtraSidecar) -> Result<(), String> {
+    log::info!("Spawning {} sidecar…", extra.name);
+    let cwd = sidecar_cwd(config)?;
+    let port = *app.state::<RequestedPort>().0.lock().unwrap_or_else(PoisonError::into_inner);
+    let (mut rx, child) = app
+        .shell()
+        .sidecar(&extra.name)
+        .map_err(|e| format!("failed to create {} sidecar command: {}", extra.name, e))?
+        .current_dir(cwd)
+        .args(&extra.args)
+        .envs(sidecar_env(config, port, Vec::new(), app.state::<AuthToken>().get()))
+        .spawn()
+        .map_err(|e| format!("failed to spawn {} sidecar: {}", extra.name, e))?;
+    let pid = child.pid();
+    let exited = Arc::new(AtomicBool::new(false));
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::SeqCst);
+    #[cfg(windows)]
+    kill_with_parent(pid);
+    app.state::<Sidecars>().lock().push(NamedChild {
+        name: extra.name.clone(),
+        child: SidecarChild::new(child),
+        exited: exited.clone(),
+        generation,
+        shutdown_timeout: Duration::from_secs(extra.shutdown_timeout_secs),
+    });
+    log::info!("{} sidecar spawned (pid {})", extra.name, pid);
+
+    let app = app.clone();
+    let name = extra.name.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
+                    let text = crate::logs::decode_line(&app, &line);
+                    log::info!("[{}] {}", name, app.state::<Redactor>().redact(text.trim_end()));
+                }
+                CommandEvent::Terminated(status) => {
+                    exited.store(true, Ordering::SeqCst);
+                    if app.state::<Sidecars>().remove(generation) {
+                        log::warn!("[{}] pid {} exited: {}", name, pid, describe_exit(&status));
+                    }
+                    break;
+                }
+                CommandEvent::Error(err) => log::error!("[{}] error: {}", name, err),
+                _ => {}
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Kill the current sidecar, if any, and mark the backend stopped. The
+/// child's log-streaming task sees the resulting `Terminated` event and exits
+/// on its own.
+pub(crate) fn kill_backend(app: &AppHandle) {
+    if let Some(sidecar) = app.state::<Sidecars>().take() {
+        log::info!("Killing {} sidecar (pid {})…", sidecar_name(), sidecar.child.pid());
+        let _ = sidecar.child.kill();
+        remove_pid_file();
+        set_status(app, BackendStatus::Stopped);
+    }
+}
+
+/// Stop the sidecar politely and give it `shutdown_timeout_secs` to exit
+/// before killing it outright, then wait up to 3s more for the kill to take
+/// effect. The polite request is a `POST` to `shutdown_path`, so the backend
+/// can run its teardown; a backend without that endpoint gets SIGTERM on Unix.
+/// Blocks the calling thread, so off app exit it belongs in `spawn_blocking`.
+pub(crate) fn shutdown_backend(app: &AppHandle) {
+    if let Some(sidecar) = app.state::<Sidecars>().take() {
+        shutdown(app, sidecar);
+    }
+}
+
+/// Stop every sidecar in reverse start order, so extras go before the
+/// backend they depend on, each within its own graceful timeout. Blocks the
+/// calling thread, so it's meant for app exit.
+pub(crate) fn shutdown_all(app: &AppHandle) {
+    for sidecar in app.state::<Sidecars>().take_all().into_iter().rev() {
+        shutdown(app, sidecar);
+    }
+}
+
+/// The sequence behind [`shutdown_backend`]; extras only get SIGTERM, and
+/// only the backend's PID file and status are updated.
+fn shutdown(app: &AppHandle, sidecar: NamedChild) {
+    let backend = sidecar.is_backend();
+    let name = sidecar.name.as_str();
+    let pid = sidecar.child.pid();
+    let timeout = sidecar.shutdown_timeout;
+
+    let requested = if backend && request_shutdown(app) {
+        Some("shutdown request")
+    } else if terminate(name, pid) {
+        Some("SIGTERM")
+    } else {
+        None
+    };
+    match requested {
+        Some(how) => {
+            if wait_for_exit(&sidecar.exited, timeout) {
+                log::info!("{} (pid {}) shut down gracefully after {}", name, pid, how);
+                if backend {
+                    remove_pid_file();
+                    set_status(app, BackendStatus::Stopped);
+                }
+                return;
+            }
+            log::warn!(
+                "{} (pid {}) still running {}s after {}, killing it",
+                name,
+                pid,
+                timeout.as_secs(),
+                how
+            );
+        }
+        None => {
+            log::warn!("Graceful shutdown of {} (pid {}) unavailable, killing it", name, pid);
+        }
+    }
+    let _ = sidecar.child.kill();
+    // Don't let the app exit (and the OS tear us down) before the kill lands.
+    const KILL_TIMEOUT: Duration = Duration::from_secs(3);
+    if !wait_for_exit(&sidecar.exited, KILL_TIMEOUT) {
+        // Keep the PID file so the next launch can reap it.
+        log::warn!(
+            "{} (pid {}) not confirmed dead {}s after kill, it may be orphaned",
+            name,
+            pid,
+            KILL_TIMEOUT.as_secs()
+        );
+    } else if backend {
+        remove_pid_file();
+    }
+    if backend {
+        set_status(app, BackendStatus::Stopped);
+    }
+}
+
+/// Records the running sidecar's PID so a later launch can clean up after a
+/// force-quit that skipped exit handling.
+fn pid_file() -> PathBuf {
+    crate::state_dir().join("backend.pid")
+}
+
+/// Its command line, so a stray can be recognised even when it isn't named
+/// after the sidecar (script mode runs an interpreter).
+fn cmd_file() -> PathBuf {
+    crate::state_dir().join("backend.cmd")
+}
+
+fn write_pid_file(pid: u32) {
+    let path = pid_file();
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, pid.to_string()));
+    if let Err(e) = written {
+        log::warn!("Failed to write {}: {}", path.display(), e);
+    }
+    let Some(cmd) = command_line(pid) else {
+        let _ = std::fs::remove_file(cmd_file());
+        return;
+    };
+    let json = serde_json::to_string(&cmd).unwrap_or_default();
+    if let Err(e) = std::fs::write(cmd_file(), json) {
+        log::warn!("Failed to write {}: {}", cmd_file().display(), e);
+    }
+}
+
+fn remove_pid_file() {
+    let _ = std::fs::remove_file(pid_file());
+    let _ = std::fs::remove_file(cmd_file());
+}
+
+/// The command line of process `pid`, if it exists and can be read.
+fn command_line(pid: u32) -> Option<Vec<String>> {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    let refresh = ProcessRefreshKind::nothing().with_cmd(UpdateKind::Always);
+    system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, refresh);
+    let cmd = system.process(pid)?.cmd();
+    (!cmd.is_empty()).then(|| cmd.iter().map(|arg| arg.to_string_lossy().into_owned()).collect())
+}
+
+/// The PID recorded in `backend.pid`, if any.
+pub(crate) fn recorded_pid() -> Option<u32> {
+    std::fs::read_to_string(pid_file()).ok()?.trim().parse().ok()
+}
+
+/// Whether a process with `pid` exists at all.
+pub(crate) fn process_exists(pid: u32) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    system.process(pid).is_some()
+}
+
+/// The backend's process is gone but no `Terminated` event said so (e.g. it
+/// was detached or replaced): drop its handle, go `Stopped` with `reason`,
+/// and take the auto-restart path as after a crash.
+pub(crate) fn backend_vanished(app: &AppHandle, reason: String) {
+    app.state::<Sidecars>().take();
+    remove_pid_file();
+    set_stopped(app, &reason);
+    if app.state::<AutoRestart>().enabled() {
+        tauri::async_runtime::spawn(auto_restart(app.clone(), reason));
+    }
+}
+
+/// Kill a sidecar orphaned by a previous launch that was force-quit, as
+/// recorded in `backend.pid`. Only a process still running the command line
+/// recorded in `backend.cmd` (or, without one, named like our sidecar) is
+/// touched, since the PID may have been reused since.
+pub(crate) fn reap_stale_sidecar() {
+    let Some(pid) = recorded_pid() else {
+        return;
+    };
+
+    let recorded_cmd = std::fs::read_to_string(cmd_file())
+        .ok()
+        .and_then(|json| serde_json::from_str::<Vec<String>>(&json).ok());
+    let ours = |process: &sysinfo::Process| {
+        let cmd: Vec<String> =
+            process.cmd().iter().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        match &recorded_cmd {
+            Some(recorded) => *recorded == cmd,
+            None => process.name().to_string_lossy().starts_with(sidecar_name()),
+        }
+    };
+
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    let refresh = ProcessRefreshKind::nothing().with_cmd(UpdateKind::Always);
+    system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, refresh);
+    if let Some(process) = system.process(pid) {
+        if ours(process) {
+            log::warn!("Killing {} (pid {}) orphaned by a previous launch", sidecar_name(), pid);
+            process.kill();
+        }
+    }
+    remove_pid_file();
+}
+
+/// Put the sidecar in a job object that is closed, killing everything in it,
+/// when this process exits for any reason — including being force-killed.
+#[cfg(windows)]
+fn kill_with_parent(pid: u32) {
+    use std::sync::OnceLock;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+    use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE};
+
+    // One job for the app's lifetime. Its handle is deliberately never closed:
+    // the OS closes it when we die, which is what kills the sidecar.
+    static JOB: OnceLock<usize> = OnceLock::new();
+    // SAFETY: plain Win32 calls; `info` outlives the call that reads it.
+    let job = *JOB.get_or_init(|| unsafe {
+        let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+        if job.is_null() {
+            return 0;
+        }
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &info as *const _ as *const std::ffi::c_void,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        );
+        job as usize
+    });
+    if job == 0 {
+        log::warn!("Failed to create job object; {} may outlive a crash", sidecar_name());
+        return;
+    }
+
+    // SAFETY: `process` is a handle we just opened and close before returning.
+    unsafe {
+        let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+        if process.is_null() {
+            log::warn!("Failed to open {} (pid {}) to tie it to the app", sidecar_name(), pid);
+            return;
+        }
+        if AssignProcessToJobObject(job as HANDLE, process) == 0 {
+            log::warn!("Failed to tie {} (pid {}) to the app's lifetime", sidecar_name(), pid);
+        }
+        CloseHandle(process);
+    }
+}
+
+/// `POST` to the backend's `shutdown_path`. Returns whether the backend
+/// accepted; a missing endpoint or an unreachable backend means no.
+fn request_shutdown(app: &AppHandle) -> bool {
+    let config = app.state::<BrianConfig>();
+    let connection = match crate::read_backend_connection(app) {
+        Ok(connection) => connection,
+        Err(e) => {
+            log::info!("Not requesting backend shutdown: {}", e);
+            return false;
+        }
+    };
+    let url = format!("{}{}", connection.base_url(), config.shutdown_path);
+    log::info!("Requesting backend shutdown via POST {}…", url);
+    let client = crate::http_client(&config).build().unwrap_or_default();
+    let request = connection
+        .authorize(client.post(&url))
+        .timeout(Duration::from_secs(2))
+        .send();
+    match tauri::async_runtime::block_on(request) {
+        Ok(resp) if resp.status().is_success() => true,
+        Ok(resp) => {
+            log::info!("Shutdown request returned {}", resp.status());
+            false
+        }
+        Err(e) => {
+            log::info!("Shutdown request failed: {}", e);
+            false
+        }
+    }
+}
+
+/// How `reload_backend_config` got the backend to reload.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ReloadMethod {
+    Endpoint,
+    Sighup,
+}
+
+/// How long the backend gets to answer a reload request.
+const RELOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ask the backend to re-read its config: `POST` to `reload_path`, and
+/// with `reload_sighup`, SIGHUP when the backend has no such endpoint. Fails
+/// with `Unsupported` when neither applies.
+pub(crate) async fn reload_config(app: &AppHandle) -> Result<ReloadMethod, CommandError> {
+    let connection = crate::read_backend_connection(app)?;
+    let (url, sighup) = {
+        let config = app.state::<BrianConfig>();
+        let url = (!config.reload_path.is_empty())
+            .then(|| format!("{}{}", connection.base_url(), config.reload_path));
+        (url, config.reload_sighup)
+    };
+    if let Some(url) = url {
+        log::info!("Asking backend to reload its config via POST {}…", url);
+        let request = app.state::<BackendClient>().0.post(&url).timeout(RELOAD_TIMEOUT);
+        let request = connection.authorize(request);
+        match request.send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(ReloadMethod::Endpoint),
+            // No such endpoint; SIGHUP may still do.
+            Ok(resp) if matches!(resp.status().as_u16(), 404 | 405 | 501) => {
+                log::info!("Backend has no reload endpoint ({})", resp.status());
+            }
+            Ok(resp) => return Err(format!("reload request returned {}", resp.status()).into()),
+            Err(e) => return Err(format!("reload request failed: {}", e).into()),
+        }
+    }
+    if sighup && cfg!(unix) {
+        let pid = app
+            .state::<Sidecars>()
+            .pid()
+            .ok_or_else(|| format!("{} isn't running", sidecar_name()))?;
+        return if hangup(pid) {
+            Ok(ReloadMethod::Sighup)
+        } else {
+            Err(format!("failed to send SIGHUP to pid {}", pid).into())
+        };
+    }
+    Err(CommandError::unsupported(
+        "backend has no reload endpoint; set reload_path or reload_sighup in config.toml",
+    ))
+}
+
+/// Send SIGHUP. Returns whether the signal was delivered.
+#[cfg(unix)]
+fn hangup(pid: u32) -> bool {
+    log::info!("Sending SIGHUP to {} (pid {})…", sidecar_name(), pid);
+    // SAFETY: kill(2) takes plain integers and has no memory-safety requirements.
+    unsafe { libc::kill(pid as libc::pid_t, libc::SIGHUP) == 0 }
+}
+
+#[cfg(not(unix))]
+fn hangup(_pid: u32) -> bool {
+    false
+}
+
+/// Send SIGTERM. Returns whether the signal was delivered.
+#[cfg(unix)]
+fn terminate(name: &str, pid: u32) -> bool {
+    log::info!("Sending SIGTERM to {} (pid {})…", name, pid);
+    // SAFETY: kill(2) takes plain integers and has no memory-safety requirements.
+    unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) == 0 }
+}
+
+/// There is no SIGTERM equivalent here; only the shutdown request is polite.
+#[cfg(not(unix))]
+fn terminate(_name: &str, _pid: u32) -> bool {
+    false
+}
+
+/// Block until `exited` is set or `timeout` elapses; returns whether it exited.
+fn wait_for_exit(exited: &AtomicBool, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while !exited.load(Ordering::SeqCst) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    true
+}
+
+/// The backend's spawn generation, or [`ATTACHED`] if none is held.
+pub(crate) fn current_generation(app: &AppHandle) -> u64 {
+    let state = app.state::<Sidecars>();
+    let guard = state.lock();
+    guard
+        .iter()
+        .find(|sidecar| sidecar.is_backend())
+        .map_or(ATTACHED, |sidecar| sidecar.generation)
+}
+
+/// Whether spawn `generation` is still the sidecar held in managed state.
+/// Once a child has been taken out (restart, window close) its termination
+/// was our doing, and its tasks must not touch the status any more.
+pub(crate) fn is_current_sidecar(app: &AppHandle, generation: u64) -> bool {
+    let state = app.state::<Sidecars>();
+    let guard = state.lock();
+    match guard.iter().find(|sidecar| sidecar.is_backend()) {
+        Some(sidecar) => sidecar.generation == generation,
+        None => generation == ATTACHED,
+    }
+}
+
+/// The port in a stdout `line` like `BRIAN_PORT=12345`: the digits right
+/// after the first of `markers` found in it.
+fn announced_port(line: &str, markers: &[String]) -> Option<u16> {
+    markers.iter().find_map(|marker| {
+        let rest = &line[line.find(marker.as_str())? + marker.len()..];
+        let digits = rest.trim_start();
+        let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+        digits[..end].parse().ok().filter(|&port| port != 0)
+    })
+}
+
+/// Whether a stderr `line` reports that the backend couldn't bind its port.
+fn is_port_conflict(line: &str, patterns: &[String]) -> bool {
+    let line = line.to_lowercase();
+    patterns.iter().any(|p| line.contains(&p.to_lowercase()))
+}
+
+/// The port a bind-failure `line` complains about: the last number in it that
+/// is a valid port (messages typically end with the address), otherwise the
+/// port we expected the backend to use, if we know it.
+fn conflicting_port(app: &AppHandle, line: &str) -> Option<u16> {
+    line.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse::<u16>().ok())
+        .rfind(|&n| n != 0)
+        .or_else(|| crate::read_backend_port(app).ok())
+}
+
+/// The sidecar can't bind its port. Restarting won't help, so fail fast with
+/// `backend-port-conflict` rather than letting the health check run out its
+/// budget.
+fn report_port_conflict(app: &AppHandle, line: &str) {
+    let port = conflicting_port(app, line);
+    let which = port.map_or_else(|| "its port".to_string(), |port| format!("port {}", port));
+    log::error!("{} could not bind {}: already in use", sidecar_name(), which);
+    let _ = app.emit("backend-port-conflict", port);
+    set_status(
+        app,
+        BackendStatus::unhealthy(
+            ErrorKind::PortConflict,
+            format!("{} is already in use", which),
+            Some(line.trim().to_string()),
+        ),
+    );
+}
+
+/// Stream sidecar stdout / stderr to the app log, `backend.log`, and
+/// `backend-log` events until the process terminates, watching stderr for
+/// port conflicts.
+fn spawn_log_stream(
+    app: AppHandle,
+    mut rx: Receiver<CommandEvent>,
+    pid: u32,
+    generation: u64,
+    exited: Arc<AtomicBool>,
+) {
+    tauri::async_runtime::spawn(async move {
+        let mut batch = LogBatch::default();
+        let mut port_conflict = false;
+        loop {
+            let event = tokio::select! {
+                event = rx.recv() => event,
+                _ = batch.due() => {
+                    batch.flush(&app);
+                    continue;
+                }
+            };
+            let Some(event) = event else {
+                batch.flush(&app);
+                break;
+            };
+
+            match event {
+                CommandEvent::Stdout(line) => {
+                    // Ports are read from the raw line, in case a pattern is too eager.
+                    record_line(&app, &mut batch, "stdout", &line);
+                    let text = String::from_utf8_lossy(&line);
+                    let config = app.state::<BrianConfig>();
+                    if let Some(port) = announced_port(&text, &config.port_markers) {
+                        if app.state::<StdoutPort>().get() != Some(port) {
+                            log::info!("{} announced port {} on stdout", sidecar_name(), port);
+                            app.state::<StdoutPort>().set(Some(port));
+                        }
+                    }
+                }
+                CommandEvent::Stderr(line) => {
+                    // Redacted, since a conflict line ends up in the status.
+                    let text = record_line(&app, &mut batch, "stderr", &line);
+                    if !port_conflict
+                        && is_port_conflict(&text, &app.state::<BrianConfig>().port_conflict_patterns)
+                    {
+                        port_conflict = true;
+                        report_port_conflict(&app, &text);
+                    }
+                }
+                CommandEvent::Terminated(status) => {
+                    exited.store(true, Ordering::SeqCst);
+                    batch.flush(&app);
+                    if !is_current_sidecar(&app, generation)
+                        || app.state::<ShuttingDown>().0.load(Ordering::SeqCst)
+                    {
+                        log::info!(
+                            "[{}] pid {} exited after being stopped: {:?}",
+                            sidecar_name(),
+                            pid,
+                            status
+                        );
+                        break;
+                    }
+                    // The child is gone; drop its handle so nothing tries to kill it later.
+                    app.state::<Sidecars>().take();
+                    remove_pid_file();
+
+                    if status.code == Some(0) {
+                        // Exited on its own terms, e.g. a backend that stops itself.
+                        log::info!("[{}] pid {} exited cleanly", sidecar_name(), pid);
+                        set_status(&app, BackendStatus::Stopped);
+                    } else {
+                        log::warn!(
+                            "[{}] process terminated with status: {:?}",
+                            sidecar_name(),
+                            status
+                        );
+                        if port_conflict {
+                            // Already reported; respawning would hit the same busy port.
+                        } else if app.state::<AutoRestart>().enabled() {
+                            auto_restart(app, describe_exit(&status)).await;
+                        } else {
+                            set_status(
+                                &app,
+                                BackendStatus::unhealthy(
+                                    ErrorKind::Terminated,
+                                    "sidecar process terminated unexpectedly",
+                                    Some(describe_exit(&status)),
+                                ),
+                            );
+                        }
+                    }
+                    break;
+                }
+                CommandEvent::Error(err) => {
+                    log::error!("[{}] error: {}", sidecar_name(), err);
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+/// `"code 1"`, `"signal 9"`, … for a terminated sidecar.
+fn describe_exit(status: &TerminatedPayload) -> String {
+    match (status.code, status.signal) {
+        (Some(code), _) => format!("code {}", code),
+        (None, Some(signal)) => format!("signal {}", signal),
+        (None, None) => "unknown".to_string(),
+    }
+}
+
+/// Respawn the sidecar after a crash that exited with `exit`, backing off
+/// between attempts, until it comes back or the restart budget is exhausted.
+/// Too many crashes within the configured window trip a breaker instead:
+/// `backend-crash-loop` is emitted with the recent exit statuses and nothing
+/// is restarted until `restart_backend` is called. With
+/// `auto_restart_confirm`, that's always the case: `backend-restart-suggested`
+/// (with the exit status) replaces the respawn.
+async fn auto_restart(app: AppHandle, exit: String) {
+    let restart = app.state::<AutoRestart>();
+    if restart.tripped() {
+        set_status(
+            &app,
+            BackendStatus::unhealthy(ErrorKind::Terminated, "backend is crash-looping", Some(exit)),
+        );
+        return;
+    }
+    if let Some(recent) = restart.record_crash(exit.clone()) {
+        log::error!(
+            "{} crashed {} times within {}s ({}), not restarting it again",
+            sidecar_name(),
+            recent.len(),
+            restart.crash_loop_window.as_secs(),
+            recent.join(", ")
+        );
+        let _ = app.emit("backend-crash-loop", &recent);
+        set_status(
+            &app,
+            BackendStatus::unhealthy(
+                ErrorKind::Terminated,
+                format!("backend is crash-looping ({} crashes)", recent.len()),
+                Some(recent.join(", ")),
+            ),
+        );
+        return;
+    }
+    if restart.confirm {
+        log::warn!(
+            "{} exited ({}), waiting for a restart to be confirmed",
+            sidecar_name(),
+            exit
+        );
+        let _ = app.emit("backend-restart-suggested", &exit);
+        set_status(
+            &app,
+            BackendStatus::unhealthy(
+                ErrorKind::Terminated,
+                "sidecar process terminated unexpectedly",
+                Some(exit),
+            ),
+        );
+        return;
+    }
+
+    loop {
+        let Some(attempt) = app.state::<AutoRestart>().next_attempt() else {
+            log::error!(
+                "{} crashed {} times in a row, giving up on auto-restart",
+                sidecar_name(),
+                AutoRestart::MAX_ATTEMPTS
+            );
+            set_status(
+                &app,
+                BackendStatus::unhealthy(
+                    ErrorKind::Terminated,
+                    "sidecar process terminated unexpectedly",
+                    Some(exit),
+                ),
+            );
+            return;
+        };
+
+        set_status(&app, BackendStatus::LAUNCHING);
+        let _ = app.emit("backend-restarting", attempt);
+        let delay = app.state::<AutoRestart>().delay * (1 << (attempt - 1));
+        log::info!(
+            "Auto-restarting {} in {}s (attempt {}/{})…",
+            sidecar_name(),
+            delay.as_secs(),
+            attempt,
+            AutoRestart::MAX_ATTEMPTS
+        );
+        tokio::time::sleep(delay).await;
+
+        if app.state::<ShuttingDown>().0.load(Ordering::SeqCst) {
+            return;
+        }
+        if app.state::<Sidecars>().is_running() {
+            log::info!("{} was started meanwhile, cancelling auto-restart", sidecar_name());
+            return;
+        }
+        match spawn_backend_async(&app).await {
+            Ok(()) => return,
+            Err(e) => log::error!("Auto-restart attempt {} failed: {}", attempt, e),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Startup splash shown while the backend boots. The main window starts
+//! hidden and is only revealed once the backend is ready and the webview has
+//! painted (it calls `app_ready`), so a cold start neither flashes an app
+//! that can't reach its backend yet nor an empty window.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tauri::{AppHandle, Listener, Manager, WebviewUrl, WebviewWindowBuilder};
+
+use crate::config::BrianConfig;
+
+pub(crate) const LABEL: &str = "splash";
+
+/// How long past the health check's budget the main window is revealed
+/// even if the backend never became ready; the app's own connection UI
+/// takes over from there.
+const REVEAL_SLACK: Duration = Duration::from_secs(15);
+
+/// How long after `backend-ready` the webview gets to call `app_ready`
+/// before the main window is shown regardless.
+const APP_READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Managed state recording which half of the reveal condition has happened.
+#[derive(Default)]
+pub(crate) struct RevealGate {
+    backend: AtomicBool,
+    frontend: AtomicBool,
+}
+
+/// The webview has rendered; reveal the main window if the backend is
+/// ready too.
+pub(crate) fn app_ready(app: &AppHandle) {
+    let gate = app.state::<RevealGate>();
+    if !gate.frontend.swap(true, Ordering::SeqCst) {
+        log::info!("Main window has rendered");
+    }
+    if gate.backend.load(Ordering::SeqCst) {
+        reveal_main(app);
+    }
+}
+
+fn backend_ready(app: &AppHandle) {
+    let gate = app.state::<RevealGate>();
+    gate.backend.store(true, Ordering::SeqCst);
+    if gate.frontend.load(Ordering::SeqCst) {
+        reveal_main(app);
+        return;
+    }
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(APP_READY_TIMEOUT).await;
+        if handle.get_webview_window(LABEL).is_some() {
+            log::warn!(
+                "Main window hasn't called app_ready {}s after the backend, showing it anyway",
+                APP_READY_TIMEOUT.as_secs()
+            );
+            reveal_main(&handle);
+        }
+    });
+}
+
+/// Open the splash window and reveal the main window once both
+/// `backend-ready` and `app_ready` have happened, or [`REVEAL_SLACK`] after
+/// the longest startup budget (a first run's `cold_start_budget_secs`), so a
+/// slow first start isn't cut short. Failures are shown by the splash page
+/// itself, which listens for `backend-error` and offers a retry.
+pub(crate) fn create(app: &AppHandle) -> tauri::Result<()> {
+    WebviewWindowBuilder::new(app, LABEL, WebviewUrl::App("splash.html".into()))
+        .title("Brian")
+        .inner_size(360.0, 240.0)
+        .resizable(false)
+        .decorations(false)
+        .center()
+        .build()?;
+
+    let handle = app.clone();
+    app.listen_any("backend-ready", move |_| backend_ready(&handle));
+
+    let config = app.state::<BrianConfig>();
+    let budget = Duration::from_secs(config.health_budget_secs.max(config.cold_start_budget_secs));
+    let reveal_timeout = budget + REVEAL_SLACK;
+    let handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(reveal_timeout).await;
+        if handle.get_webview_window(LABEL).is_some() {
+            log::warn!(
+                "Backend not ready after {}s, showing the main window anyway",
+                reveal_timeout.as_secs()
+            );
+            reveal_main(&handle);
+        }
+    });
+    Ok(())
+}
+
+/// Show and focus the main window and close the splash, if still open.
+/// Safe to call repeatedly.
+pub(crate) fn reveal_main(app: &AppHandle) {
+    if let Some(main) = app.get_webview_window("main") {
+        let _ = main.show();
+        let _ = main.set_focus();
+    }
+    if let Some(splash) = app.get_webview_window(LABEL) {
+        let _ = splash.close();
+    }
+}
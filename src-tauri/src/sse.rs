@@ -0,0 +1,165 @@
+//! Bridges the backend's Server-Sent Event streams onto the Tauri event bus,
+//! so the webview gets push updates without connecting to the backend itself.
+//! Each subscribed path is re-emitted as `backend-sse` events and reconnects
+//! with backoff whenever the stream drops.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::proxy::BackendClient;
+
+/// Reconnect delays start here and double up to [`MAX_BACKOFF`].
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Managed state holding the task behind each subscribed path.
+#[derive(Default)]
+pub(crate) struct SseSubscriptions(Mutex<HashMap<String, JoinHandle<()>>>);
+
+/// Payload of `backend-sse`.
+#[derive(Debug, Clone, Serialize)]
+struct SseEvent {
+    /// The subscribed path the event arrived on.
+    path: String,
+    /// The SSE `event:` field, `"message"` when the backend omits it.
+    event: String,
+    data: String,
+    id: Option<String>,
+}
+
+/// Start relaying the SSE stream at `path`. Subscribing to a path that is
+/// already relayed is a no-op.
+pub(crate) fn subscribe(app: &AppHandle, path: String) -> Result<(), String> {
+    if !path.starts_with('/') {
+        return Err(format!("path {:?} must start with '/'", path));
+    }
+    let state = app.state::<SseSubscriptions>();
+    let mut subscriptions = state.0.lock().unwrap_or_else(PoisonError::into_inner);
+    if subscriptions.contains_key(&path) {
+        return Ok(());
+    }
+    log::info!("Relaying backend events from {}", path);
+    let task = tauri::async_runtime::spawn(relay(app.clone(), path.clone()));
+    subscriptions.insert(path, task);
+    Ok(())
+}
+
+/// Stop relaying `path`. Returns whether it was subscribed.
+pub(crate) fn unsubscribe(app: &AppHandle, path: &str) -> bool {
+    let state = app.state::<SseSubscriptions>();
+    let task = state.0.lock().unwrap_or_else(PoisonError::into_inner).remove(path);
+    match task {
+        Some(task) => {
+            log::info!("Stopped relaying backend events from {}", path);
+            task.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Keep a stream for `path` open until aborted, reconnecting after drops.
+async fn relay(app: AppHandle, path: String) {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut last_id: Option<String> = None;
+    loop {
+        match stream(&app, &path, &mut last_id).await {
+            // Got at least one event before dropping; start the backoff over.
+            Ok(true) => backoff = INITIAL_BACKOFF,
+            Ok(false) => {}
+            Err(e) => log::warn!("Backend event stream {} failed: {}", path, e),
+        }
+        log::debug!("Reconnecting to {} in {}s…", path, backoff.as_secs());
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Read one connection's worth of events, resuming after `last_id`. Returns
+/// whether any arrived.
+async fn stream(
+    app: &AppHandle,
+    path: &str,
+    last_id: &mut Option<String>,
+) -> Result<bool, String> {
+    let connection = crate::read_backend_connection(app)?;
+    let url = format!("{}{}", connection.base_url(), path);
+    let request = app.state::<BackendClient>().0.get(&url).header("Accept", "text/event-stream");
+    let mut request = connection.authorize(request);
+    if let Some(id) = last_id.as_deref() {
+        request = request.header("Last-Event-ID", id);
+    }
+    let mut resp = request.send().await.map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("status {}", resp.status().as_u16()));
+    }
+
+    let mut parser = Parser::default();
+    let mut buf = Vec::new();
+    let mut received = false;
+    while let Some(chunk) = resp.chunk().await.map_err(|e| e.to_string())? {
+        buf.extend_from_slice(&chunk);
+        while let Some(end) = buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buf.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some((event, data, id)) = parser.line(line.trim_end_matches(['\r', '\n'])) else {
+                continue;
+            };
+            if id.is_some() {
+                *last_id = id;
+            }
+            received = true;
+            let _ = app.emit(
+                "backend-sse",
+                SseEvent {
+                    path: path.to_string(),
+                    event,
+                    data,
+                    id: last_id.clone(),
+                },
+            );
+        }
+    }
+    Ok(received)
+}
+
+/// Accumulates SSE fields line by line until a blank line dispatches them.
+#[derive(Default)]
+struct Parser {
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl Parser {
+    /// Feed one line; returns `(event, data, id)` when it completes an event.
+    fn line(&mut self, line: &str) -> Option<(String, String, Option<String>)> {
+        if line.is_empty() {
+            let id = self.id.take();
+            let event = self.event.take();
+            if self.data.is_empty() {
+                return None;
+            }
+            let data = std::mem::take(&mut self.data).join("\n");
+            return Some((event.unwrap_or_else(|| "message".to_string()), data, id));
+        }
+        if line.starts_with(':') {
+            // A comment, typically a keep-alive.
+            return None;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+}
@@ -0,0 +1,292 @@
+//! The backend status machine. Every lifecycle event the frontend sees is
+//! derived from a transition here, so the events and `backend_status` can
+//! never disagree. Each transition is also mirrored to `status.json` in the
+//! state dir for scripts that can't use IPC.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::watch;
+
+/// Where the backend is in its lifecycle.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub(crate) enum BackendStatus {
+    /// Spawned (or attached to) and not yet ready to serve requests.
+    Starting { phase: StartupPhase },
+    /// Passed its health check on `port`.
+    Healthy { port: u16 },
+    /// Failed to start or died unexpectedly. `reason` is for display; UIs
+    /// branch on `kind`.
+    Unhealthy {
+        kind: ErrorKind,
+        reason: String,
+        /// Supporting detail, e.g. the exit status or the last probe failure.
+        detail: Option<String>,
+        /// The sidecar's last output lines, for failures they help explain.
+        log_tail: Vec<String>,
+    },
+    /// Deliberately not running.
+    Stopped,
+}
+
+/// How far a `Starting` backend has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StartupPhase {
+    /// Waiting for `/health` to answer.
+    Launching,
+    /// Alive (`/health` passes) but `/ready` doesn't yet, e.g. while the
+    /// database is being migrated.
+    Migrating,
+}
+
+/// What kind of failure made the backend unhealthy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorKind {
+    /// The sidecar couldn't be launched at all.
+    SpawnFailed,
+    /// It launched but never passed its health or readiness check.
+    HealthTimeout,
+    /// Its port is taken by something else.
+    PortConflict,
+    /// It exited unexpectedly (or kept crashing).
+    Terminated,
+    /// It was healthy, then stopped answering heartbeats.
+    Unresponsive,
+    /// There's no home directory (and no `BRIAN_HOME`) to keep state in.
+    NoHomeDir,
+    /// `pre_spawn_command` failed, so the sidecar wasn't started.
+    PreSpawnFailed,
+    /// There's no port file and `require_port_file` forbids guessing.
+    NoPortFile,
+}
+
+impl ErrorKind {
+    /// Whether the backend stays down without someone stepping in, as
+    /// opposed to an `Unresponsive` heartbeat that may still recover. Only
+    /// these get a crash bundle, so a flapping backend can't push out the
+    /// bundles of real failures.
+    fn is_final(self) -> bool {
+        match self {
+            Self::SpawnFailed
+            | Self::HealthTimeout
+            | Self::PortConflict
+            | Self::PreSpawnFailed
+            | Self::NoPortFile
+            // Only reported once auto-restart has given up, or is off.
+            | Self::Terminated => true,
+            Self::Unresponsive | Self::NoHomeDir => false,
+        }
+    }
+}
+
+/// Payload of `backend-error`.
+#[derive(Debug, Clone, Serialize)]
+struct BackendError<'a> {
+    kind: ErrorKind,
+    message: &'a str,
+    detail: Option<&'a str>,
+    log_tail: &'a [String],
+}
+
+impl BackendStatus {
+    /// The state right after a spawn.
+    pub const LAUNCHING: Self = Self::Starting {
+        phase: StartupPhase::Launching,
+    };
+
+    pub fn unhealthy(kind: ErrorKind, reason: impl Into<String>, detail: Option<String>) -> Self {
+        Self::Unhealthy {
+            kind,
+            reason: reason.into(),
+            detail,
+            log_tail: Vec::new(),
+        }
+    }
+
+    /// Attach `lines` of sidecar output to an `Unhealthy` status.
+    pub fn with_log_tail(mut self, lines: Vec<String>) -> Self {
+        if let Self::Unhealthy { log_tail, .. } = &mut self {
+            *log_tail = lines;
+        }
+        self
+    }
+}
+
+/// Managed state holding the current [`BackendStatus`], on a watch channel
+/// so callers can await transitions instead of polling.
+pub(crate) struct BackendState(watch::Sender<BackendStatus>);
+
+impl BackendState {
+    pub fn new(status: BackendStatus) -> Self {
+        Self(watch::Sender::new(status))
+    }
+
+    pub fn get(&self) -> BackendStatus {
+        self.0.borrow().clone()
+    }
+
+    /// Wait until `done` accepts the status, which may already be the case.
+    pub async fn wait_until(&self, done: impl FnMut(&BackendStatus) -> bool) -> BackendStatus {
+        let mut statuses = self.0.subscribe();
+        let status = statuses.wait_for(done).await;
+        // The sender lives as long as `self`, so the channel can't close here.
+        status.map_or(BackendStatus::Stopped, |status| status.clone())
+    }
+}
+
+/// One entry of [`StatusHistory`].
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct StatusEntry {
+    /// Milliseconds since the Unix epoch.
+    pub at: u64,
+    pub status: BackendStatus,
+}
+
+/// Managed record of the latest transitions, for crash bundles.
+#[derive(Default)]
+pub(crate) struct StatusHistory(Mutex<VecDeque<StatusEntry>>);
+
+impl StatusHistory {
+    const CAPACITY: usize = 50;
+
+    fn push(&self, status: &BackendStatus) {
+        let mut entries = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.len() == Self::CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(StatusEntry {
+            at: now_millis(),
+            status: status.clone(),
+        });
+    }
+
+    /// Oldest first.
+    pub fn entries(&self) -> Vec<StatusEntry> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).iter().cloned().collect()
+    }
+}
+
+/// Managed flag for offline mode: entered when the backend goes `Unhealthy`
+/// or `Stopped`, left only once it's `Healthy` again, so a restart in
+/// progress keeps the UI degraded until it has succeeded.
+#[derive(Default)]
+pub(crate) struct OfflineMode(AtomicBool);
+
+impl OfflineMode {
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Follow `status`; returns the new mode if it changed.
+    fn update(&self, status: &BackendStatus) -> Option<bool> {
+        let offline = match status {
+            BackendStatus::Unhealthy { .. } | BackendStatus::Stopped => true,
+            BackendStatus::Healthy { .. } => false,
+            BackendStatus::Starting { .. } => return None,
+        };
+        (self.0.swap(offline, Ordering::SeqCst) != offline).then_some(offline)
+    }
+}
+
+/// Transition to `status`, repaint the tray, and emit the events derived
+/// from it: `backend-status` always, plus `backend-ready` (with the port) on
+/// becoming ready, `backend-error` (`{kind, message, detail, log_tail}`) on
+/// becoming unhealthy, and a neutral `backend-stopped` when it stops without
+/// anything going wrong.
+/// Entering or leaving [`OfflineMode`] emits `offline-mode` with the new mode.
+pub(crate) fn set_status(app: &AppHandle, status: BackendStatus) {
+    transition(app, status, None);
+}
+
+/// Transition to `Stopped` with `reason` as the `backend-stopped` payload
+/// (otherwise `null`), for a backend found to have gone away on its own.
+pub(crate) fn set_stopped(app: &AppHandle, reason: &str) {
+    transition(app, BackendStatus::Stopped, Some(reason));
+}
+
+fn transition(app: &AppHandle, status: BackendStatus, stop_reason: Option<&str>) {
+    let changed = app.state::<BackendState>().0.send_if_modified(|current| {
+        if *current == status {
+            return false;
+        }
+        log::info!("Backend status: {:?} → {:?}", *current, status);
+        *current = status.clone();
+        true
+    });
+    if !changed {
+        return;
+    }
+
+    app.state::<StatusHistory>().push(&status);
+    crate::tray::update(app, &status);
+    write_status_file(app, &status);
+    let _ = app.emit("backend-status", &status);
+    if let Some(offline) = app.state::<OfflineMode>().update(&status) {
+        log::info!("{} offline mode", if offline { "Entering" } else { "Leaving" });
+        let _ = app.emit("offline-mode", offline);
+    }
+    match status {
+        BackendStatus::Healthy { port } => {
+            crate::remember_healthy_port(port);
+            let _ = app.emit("backend-ready", port);
+        }
+        BackendStatus::Unhealthy {
+            kind,
+            ref reason,
+            ref detail,
+            ref log_tail,
+        } => {
+            let error = BackendError {
+                kind,
+                message: reason,
+                detail: detail.as_deref(),
+                log_tail,
+            };
+            let _ = app.emit("backend-error", error);
+            if kind.is_final() {
+                crate::crash::spawn_write(app, status.clone());
+            }
+        }
+        BackendStatus::Stopped => {
+            let _ = app.emit("backend-stopped", stop_reason);
+        }
+        BackendStatus::Starting { .. } => {}
+    }
+}
+
+/// Atomically replace `status.json` with `status`, the sidecar PID and a
+/// timestamp (milliseconds since the Unix epoch), e.g.
+/// `{"state":"healthy","port":8080,"pid":4242,"updated_at":1760000000000}`.
+pub(crate) fn write_status_file(app: &AppHandle, status: &BackendStatus) {
+    let pid = app.state::<crate::sidecar::Sidecars>().pid();
+    let updated_at = now_millis();
+
+    let mut json = serde_json::to_value(status).unwrap_or_default();
+    json["pid"] = pid.into();
+    json["updated_at"] = updated_at.into();
+
+    let path = crate::state_dir().join("status.json");
+    let tmp = path.with_extension("json.tmp");
+    // Write then rename, so readers never see a half-written file.
+    let written = std::fs::create_dir_all(crate::state_dir())
+        .and_then(|()| std::fs::write(&tmp, json.to_string()))
+        .and_then(|()| std::fs::rename(&tmp, &path));
+    if let Err(e) = written {
+        log::warn!("Failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// Milliseconds since the Unix epoch.
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,126 @@
+//! Disk space the backend reports at `storage_path`, polled while it's
+//! healthy so the UI can warn before the data directory fills up.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::BrianConfig;
+use crate::proxy::BackendClient;
+use crate::sidecar::is_current_sidecar;
+
+/// What the backend says about its data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StorageStats {
+    pub free_bytes: u64,
+    #[serde(default)]
+    pub total_bytes: Option<u64>,
+    /// The directory measured, if the backend says.
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// Managed latest [`StorageStats`], cleared on every spawn.
+#[derive(Default)]
+pub(crate) struct StorageState {
+    latest: Mutex<Option<StorageStats>>,
+    /// Spawn generation the poll is running for, so a re-run health check
+    /// doesn't start a second one.
+    polling: AtomicU64,
+}
+
+impl StorageState {
+    fn get(&self) -> Option<StorageStats> {
+        self.latest.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    fn set(&self, stats: Option<StorageStats>) {
+        *self.latest.lock().unwrap_or_else(PoisonError::into_inner) = stats;
+    }
+
+    pub fn clear(&self) {
+        self.set(None);
+    }
+}
+
+/// The stats from the last poll, or fetched now if there hasn't been one.
+pub(crate) async fn latest(app: &AppHandle) -> Result<Option<StorageStats>, String> {
+    match app.state::<StorageState>().get() {
+        Some(stats) => Ok(Some(stats)),
+        None => fetch(app).await,
+    }
+}
+
+/// Fresh stats from `storage_path`; `None` if the backend doesn't have the
+/// endpoint (or it's disabled).
+async fn fetch(app: &AppHandle) -> Result<Option<StorageStats>, String> {
+    let path = app.state::<BrianConfig>().storage_path.clone();
+    if path.is_empty() {
+        return Ok(None);
+    }
+    let connection = crate::read_backend_connection(app)?;
+    let url = format!("{}{}", connection.base_url(), path);
+    let request = app.state::<BackendClient>().0.get(&url).timeout(Duration::from_secs(3));
+    let resp = connection
+        .authorize(request)
+        .send()
+        .await
+        .map_err(|e| format!("GET {} failed: {}", url, e))?;
+    let stats = match resp.status() {
+        StatusCode::NOT_FOUND => None,
+        status if status.is_success() => Some(
+            resp.json::<StorageStats>()
+                .await
+                .map_err(|e| format!("malformed {} response: {}", url, e))?,
+        ),
+        status => return Err(format!("GET {} returned {}", url, status)),
+    };
+    app.state::<StorageState>().set(stats.clone());
+    Ok(stats)
+}
+
+/// Poll every `storage_interval_secs` for as long as spawn `generation` is
+/// current, emitting `backend-storage-warning` (with the stats) each time
+/// free space drops below `storage_warn_free_bytes`. Stops for good if the
+/// backend has no storage endpoint.
+pub(crate) fn spawn_poll(app: &AppHandle, generation: u64) {
+    let (interval, threshold) = {
+        let config = app.state::<BrianConfig>();
+        if config.storage_path.is_empty() || config.storage_interval_secs == 0 {
+            return;
+        }
+        (Duration::from_secs(config.storage_interval_secs), config.storage_warn_free_bytes)
+    };
+    if app.state::<StorageState>().polling.swap(generation, Ordering::SeqCst) == generation {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut warned = false;
+        while is_current_sidecar(&app, generation) {
+            match fetch(&app).await {
+                Ok(Some(stats)) => {
+                    let low = stats.free_bytes < threshold;
+                    if low && !warned {
+                        log::warn!(
+                            "Backend is low on disk space: {} bytes free",
+                            stats.free_bytes
+                        );
+                        let _ = app.emit("backend-storage-warning", &stats);
+                    }
+                    warned = low;
+                }
+                Ok(None) => {
+                    log::info!("Backend has no storage endpoint, not polling it");
+                    return;
+                }
+                Err(e) => log::debug!("Storage poll failed: {}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
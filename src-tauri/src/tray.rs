@@ -0,0 +1,102 @@
+//! System tray icon mirroring backend health, so Brian can sit minimized and
+//! still show whether the backend is alive.
+
+use std::sync::atomic::Ordering;
+
+use tauri::image::Image;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager};
+
+use crate::sidecar::{shutdown_all, sidecar_name, ShuttingDown};
+use crate::status::{BackendState, BackendStatus, StartupPhase};
+
+const TRAY_ID: &str = "brian-tray";
+
+/// Create the tray icon and its menu, reflecting the current status.
+pub(crate) fn create(app: &AppHandle) -> tauri::Result<()> {
+    let show = MenuItem::with_id(app, "show", "Show window", true, None::<&str>)?;
+    let restart = MenuItem::with_id(app, "restart", "Restart backend", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+    let menu = Menu::with_items(app, &[&show, &restart, &separator, &quit])?;
+
+    let status = app.state::<BackendState>().get();
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(icon(&status))
+        .tooltip(tooltip(&status))
+        .menu(&menu)
+        .on_menu_event(on_menu_event)
+        .build(app)?;
+    Ok(())
+}
+
+/// Repaint the tray for `status`. A no-op until the tray exists.
+pub(crate) fn update(app: &AppHandle, status: &BackendStatus) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let _ = tray.set_icon(Some(icon(status)));
+    let _ = tray.set_tooltip(Some(tooltip(status)));
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        "show" => {
+            if let Some(window) = app.get_webview_window("main") {
+                crate::close::show(&window);
+            }
+        }
+        "restart" => {
+            // Errors are already logged and reflected in the status.
+            tauri::async_runtime::spawn(crate::restart_backend(app.clone()));
+        }
+        "quit" => {
+            log::info!("Quit requested from tray, stopping {} sidecar…", sidecar_name());
+            app.state::<ShuttingDown>().0.store(true, Ordering::SeqCst);
+            shutdown_all(app);
+            app.exit(0);
+        }
+        _ => {}
+    }
+}
+
+fn tooltip(status: &BackendStatus) -> String {
+    match status {
+        BackendStatus::Starting {
+            phase: StartupPhase::Launching,
+        } => "Brian — backend starting…".to_string(),
+        BackendStatus::Starting {
+            phase: StartupPhase::Migrating,
+        } => "Brian — backend migrating…".to_string(),
+        BackendStatus::Healthy { port } => format!("Brian — backend healthy (port {})", port),
+        BackendStatus::Unhealthy { reason, .. } => format!("Brian — backend unhealthy: {}", reason),
+        BackendStatus::Stopped => "Brian — backend stopped".to_string(),
+    }
+}
+
+/// Gray while starting, green when healthy, red otherwise.
+fn icon(status: &BackendStatus) -> Image<'static> {
+    match status {
+        BackendStatus::Starting { .. } => dot([0x9e, 0x9e, 0x9e]),
+        BackendStatus::Healthy { .. } => dot([0x2e, 0xb8, 0x5c]),
+        BackendStatus::Unhealthy { .. } | BackendStatus::Stopped => dot([0xe5, 0x48, 0x4d]),
+    }
+}
+
+/// Render a filled, anti-aliased circle of `rgb` as a 32×32 RGBA image.
+fn dot(rgb: [u8; 3]) -> Image<'static> {
+    const SIZE: u32 = 32;
+    let center = (SIZE as f32 - 1.0) / 2.0;
+    let radius = SIZE as f32 / 2.0 - 2.0;
+
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let distance = (x as f32 - center).hypot(y as f32 - center);
+            let coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+            rgba.extend_from_slice(&[rgb[0], rgb[1], rgb[2], (coverage * 255.0) as u8]);
+        }
+    }
+    Image::new_owned(rgba, SIZE, SIZE)
+}
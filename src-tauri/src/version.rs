@@ -0,0 +1,76 @@
+//! The version the backend reports at `/version`, so the frontend can warn
+//! about skew between the desktop shell and the backend.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::config::BrianConfig;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum BackendVersion {
+    Known { version: String },
+    /// The backend has no `/version` endpoint.
+    Unknown,
+}
+
+/// Managed cache of the current sidecar's version, cleared on every spawn.
+#[derive(Default)]
+pub(crate) struct VersionCache(pub Mutex<Option<BackendVersion>>);
+
+impl VersionCache {
+    fn get(&self) -> Option<BackendVersion> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    fn set(&self, version: Option<BackendVersion>) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = version;
+    }
+
+    pub fn clear(&self) {
+        self.set(None);
+    }
+}
+
+/// The backend's version, from the cache or a `GET /version`. The body may be
+/// JSON with a `version` field or the bare version string.
+pub(crate) async fn fetch(app: &AppHandle) -> Result<BackendVersion, String> {
+    if let Some(version) = app.state::<VersionCache>().get() {
+        return Ok(version);
+    }
+
+    let config = app.state::<BrianConfig>().inner().clone();
+    let connection = crate::read_backend_connection(app)?;
+    let url = format!("{}/version", connection.base_url());
+    let client = crate::http_client(&config)
+        .timeout(Duration::from_secs(3))
+        .build()
+        .unwrap_or_default();
+    let resp = connection
+        .authorize(client.get(&url))
+        .send()
+        .await
+        .map_err(|e| format!("GET {} failed: {}", url, e))?;
+
+    let version = match resp.status() {
+        StatusCode::NOT_FOUND => BackendVersion::Unknown,
+        status if status.is_success() => {
+            let body = resp
+                .text()
+                .await
+                .map_err(|e| format!("failed to read {} response: {}", url, e))?;
+            let version = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| v.get("version")?.as_str().map(str::to_string))
+                .unwrap_or_else(|| body.trim().to_string());
+            BackendVersion::Known { version }
+        }
+        status => return Err(format!("GET {} returned {}", url, status)),
+    };
+    app.state::<VersionCache>().set(Some(version.clone()));
+    Ok(version)
+}
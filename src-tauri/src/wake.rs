@@ -0,0 +1,47 @@
+//! Noticing the machine waking from sleep. There's no portable suspend
+//! notification, so a thread ticks every few seconds and treats a
+//! wall-clock jump far past its tick as a sleep: the monotonic clock its
+//! sleeps run on doesn't advance while suspended, the wall clock does.
+
+use std::time::{Duration, SystemTime};
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::status::{BackendState, BackendStatus};
+
+const TICK: Duration = Duration::from_secs(5);
+
+/// Wall-clock time past a tick that counts as having slept.
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Watch for wake-ups for the app's lifetime.
+pub(crate) fn spawn(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last = SystemTime::now();
+        loop {
+            std::thread::sleep(TICK);
+            let now = SystemTime::now();
+            // A clock set backwards reads as no gap.
+            let gap = now.duration_since(last).unwrap_or_default();
+            last = now;
+            if gap > TICK + SLEEP_THRESHOLD {
+                on_resume(&app, gap);
+            }
+        }
+    });
+}
+
+/// Re-check the backend straight after a wake, since its connection may
+/// have died in the meantime. Emits `backend-resumed-checking` (with the
+/// seconds slept) so the UI can show it's reconnecting; a backend that
+/// fails this check goes `Unhealthy` at once instead of after the usual
+/// missed heartbeats.
+fn on_resume(app: &AppHandle, slept: Duration) {
+    let status = app.state::<BackendState>().get();
+    log::info!("Woke after about {}s asleep, backend was {:?}", slept.as_secs(), status);
+    if status == BackendStatus::Stopped || crate::mock::active(app) {
+        return;
+    }
+    let _ = app.emit("backend-resumed-checking", slept.as_secs());
+    crate::health::check_after_resume(app);
+}